@@ -0,0 +1,50 @@
+use khulan::site;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: khulan <command> [dir]
+
+Commands:
+  validate        Validate all content against the blueprints and print a JSON report
+  translations    Print a JSON report of missing, untranslated and outdated translations";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let dir = match args.get(1) {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::current_dir().unwrap(),
+    };
+
+    let mut site = site().dir(&dir).build();
+
+    match args.first().map(|command| command.as_str()) {
+        Some("validate") => {
+            if !site.load(&vec![]) {
+                return ExitCode::FAILURE;
+            }
+            let report = site.validate();
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            if report.valid {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Some("translations") => {
+            if !site.load(&vec![]) {
+                return ExitCode::FAILURE;
+            }
+            let report = site.translation_report();
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            if report.complete {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
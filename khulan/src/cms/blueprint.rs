@@ -0,0 +1,541 @@
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+#[cfg(feature = "kirby")]
+use yaml_rust::Yaml;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub enum FieldKind {
+    Blocks,
+    Checkboxes,
+    Color,
+    Date,
+    Email,
+    Entries,
+    Files,
+    Gap,
+    Headline,
+    Hidden,
+    Info,
+    Layout,
+    Line,
+    Link,
+    List,
+    Multiselect,
+    Number,
+    Object,
+    Pages,
+    Radio,
+    Range,
+    Select,
+    Slug,
+    Structure,
+    Tags,
+    Tel,
+    Text,
+    Textarea,
+    Time,
+    Toggle,
+    Toggles,
+    Url,
+    Users,
+    Writer,
+    Other(String),
+    #[default]
+    None,
+}
+
+impl FieldKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FieldKind::Blocks => "blocks",
+            FieldKind::Checkboxes => "checkboxes",
+            FieldKind::Color => "color",
+            FieldKind::Date => "date",
+            FieldKind::Email => "email",
+            FieldKind::Entries => "entries",
+            FieldKind::Files => "files",
+            FieldKind::Gap => "gap",
+            FieldKind::Headline => "headline",
+            FieldKind::Hidden => "hidden",
+            FieldKind::Info => "info",
+            FieldKind::Layout => "layout",
+            FieldKind::Line => "line",
+            FieldKind::Link => "link",
+            FieldKind::List => "list",
+            FieldKind::Multiselect => "multiselect",
+            FieldKind::Number => "number",
+            FieldKind::Object => "object",
+            FieldKind::Pages => "pages",
+            FieldKind::Radio => "radio",
+            FieldKind::Range => "range",
+            FieldKind::Select => "select",
+            FieldKind::Slug => "slug",
+            FieldKind::Structure => "structure",
+            FieldKind::Tags => "tags",
+            FieldKind::Tel => "tel",
+            FieldKind::Text => "text",
+            FieldKind::Textarea => "textarea",
+            FieldKind::Time => "time",
+            FieldKind::Toggle => "toggle",
+            FieldKind::Toggles => "toggles",
+            FieldKind::Url => "url",
+            FieldKind::Users => "users",
+            FieldKind::Writer => "writer",
+            FieldKind::Other(kind) => kind,
+            FieldKind::None => "",
+        }
+    }
+
+    /// fields that only decorate the panel and never store content
+    pub fn is_presentational(&self) -> bool {
+        matches!(
+            self,
+            FieldKind::Gap | FieldKind::Headline | FieldKind::Info | FieldKind::Line
+        )
+    }
+
+    /// fields that store a yaml list or structure instead of a plain string
+    pub fn is_structured(&self) -> bool {
+        matches!(
+            self,
+            FieldKind::Blocks
+                | FieldKind::Entries
+                | FieldKind::Files
+                | FieldKind::Layout
+                | FieldKind::Object
+                | FieldKind::Pages
+                | FieldKind::Structure
+                | FieldKind::Users
+        )
+    }
+}
+
+impl From<&str> for FieldKind {
+    fn from(kind: &str) -> Self {
+        match kind.trim().to_lowercase().as_str() {
+            "blocks" => FieldKind::Blocks,
+            "checkboxes" => FieldKind::Checkboxes,
+            "color" => FieldKind::Color,
+            "date" => FieldKind::Date,
+            "email" => FieldKind::Email,
+            "entries" => FieldKind::Entries,
+            "files" => FieldKind::Files,
+            "gap" => FieldKind::Gap,
+            "headline" => FieldKind::Headline,
+            "hidden" => FieldKind::Hidden,
+            "info" => FieldKind::Info,
+            "layout" => FieldKind::Layout,
+            "line" => FieldKind::Line,
+            "link" => FieldKind::Link,
+            "list" => FieldKind::List,
+            "multiselect" => FieldKind::Multiselect,
+            "number" => FieldKind::Number,
+            "object" => FieldKind::Object,
+            "pages" => FieldKind::Pages,
+            "radio" => FieldKind::Radio,
+            "range" => FieldKind::Range,
+            "select" => FieldKind::Select,
+            "slug" => FieldKind::Slug,
+            "structure" => FieldKind::Structure,
+            "tags" => FieldKind::Tags,
+            "tel" => FieldKind::Tel,
+            "text" | "title" => FieldKind::Text,
+            "textarea" => FieldKind::Textarea,
+            "time" => FieldKind::Time,
+            "toggle" => FieldKind::Toggle,
+            "toggles" => FieldKind::Toggles,
+            "url" => FieldKind::Url,
+            "users" => FieldKind::Users,
+            "writer" => FieldKind::Writer,
+            "" => FieldKind::None,
+            other => FieldKind::Other(other.to_string()),
+        }
+    }
+}
+
+impl Serialize for FieldKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FieldBlueprint {
+    pub name: String,
+    pub kind: FieldKind,
+    pub label: Option<String>,
+    pub default: Option<String>,
+    pub options: Vec<String>,
+    pub required: bool,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub pattern: Option<String>,
+    pub validate: Vec<String>,
+    pub translate: bool,
+}
+
+impl Default for FieldBlueprint {
+    fn default() -> Self {
+        Self {
+            name: "".to_string(),
+            kind: FieldKind::None,
+            label: None,
+            default: None,
+            options: vec![],
+            required: false,
+            min: None,
+            max: None,
+            min_length: None,
+            max_length: None,
+            pattern: None,
+            validate: vec![],
+            translate: true, // like in kirby
+        }
+    }
+}
+
+/// The `num` option of page blueprints, how listed pages are numbered in kirby
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub enum NumScheme {
+    /// incrementing from 1 by position
+    #[default]
+    Default,
+    /// all listed pages have `0` and are sorted otherwise
+    Zero,
+    /// the date of the field like `20241017`, from `date` or a query like `{{ page.published.toDate("Ymd") }}`
+    Date(String),
+}
+
+impl From<&str> for NumScheme {
+    fn from(num: &str) -> Self {
+        let num = num.trim();
+        match num {
+            "zero" => NumScheme::Zero,
+            "date" => NumScheme::Date("date".to_string()),
+            _ if num.starts_with("{{") && num.contains("toDate") => {
+                let field = num
+                    .trim_start_matches("{{")
+                    .trim()
+                    .trim_start_matches("page.")
+                    .split(['.', '('])
+                    .next()
+                    .unwrap_or("date")
+                    .trim()
+                    .to_lowercase();
+                NumScheme::Date(field)
+            }
+            _ => NumScheme::Default,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Blueprint {
+    pub name: String,
+    pub title: String,
+    pub fields: BTreeMap<String, FieldBlueprint>,
+    pub num: NumScheme,
+}
+
+impl Blueprint {
+    pub fn field(&self, name: &str) -> Option<&FieldBlueprint> {
+        self.fields.get(&name.to_lowercase())
+    }
+
+    /// Resolve a blueprint from its raw yaml. `sources` holds all raw blueprints of the
+    /// site keyed by their name (like `pages/post` or `fields/title`) to resolve `extends`.
+    #[cfg(feature = "kirby")]
+    pub fn from_yaml(name: &str, yaml: &Yaml, sources: &BTreeMap<String, Yaml>) -> Self {
+        let yaml = Self::resolve(yaml, sources, 0);
+        let mut fields = BTreeMap::new();
+        Self::collect_fields(&yaml, sources, &mut fields);
+
+        Self {
+            name: name.to_string(),
+            title: yaml["title"].as_str().unwrap_or(name).to_string(),
+            fields,
+            num: yaml["num"]
+                .as_str()
+                .map(NumScheme::from)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// merge the referenced blueprint of `extends` (or a plain string reference) below the node
+    #[cfg(feature = "kirby")]
+    fn resolve(yaml: &Yaml, sources: &BTreeMap<String, Yaml>, depth: usize) -> Yaml {
+        // guard against circular extends
+        if depth > 16 {
+            return yaml.clone();
+        }
+
+        match yaml {
+            Yaml::String(reference) => match sources.get(reference) {
+                Some(base) => Self::resolve(base, sources, depth + 1),
+                None => yaml.clone(),
+            },
+            Yaml::Hash(hash) => match hash.get(&Yaml::String("extends".to_string())) {
+                Some(Yaml::String(reference)) => match sources.get(reference) {
+                    Some(base) => {
+                        let base = Self::resolve(base, sources, depth + 1);
+                        let mut own = hash.clone();
+                        own.remove(&Yaml::String("extends".to_string()));
+                        Self::merge(&base, &Yaml::Hash(own))
+                    }
+                    None => yaml.clone(),
+                },
+                _ => yaml.clone(),
+            },
+            _ => yaml.clone(),
+        }
+    }
+
+    #[cfg(feature = "kirby")]
+    fn merge(base: &Yaml, over: &Yaml) -> Yaml {
+        match (base, over) {
+            (Yaml::Hash(base), Yaml::Hash(over)) => {
+                let mut merged = base.clone();
+                for (key, value) in over {
+                    let value = match merged.get(key) {
+                        Some(existing) => Self::merge(existing, value),
+                        None => value.clone(),
+                    };
+                    merged.insert(key.clone(), value);
+                }
+                Yaml::Hash(merged)
+            }
+            _ => over.clone(),
+        }
+    }
+
+    /// walk tabs, columns, sections and groups and collect all fields that store content
+    #[cfg(feature = "kirby")]
+    fn collect_fields(
+        yaml: &Yaml,
+        sources: &BTreeMap<String, Yaml>,
+        fields: &mut BTreeMap<String, FieldBlueprint>,
+    ) {
+        for layout in ["tabs", "columns", "sections"] {
+            match &yaml[layout] {
+                Yaml::Hash(hash) => hash.iter().for_each(|(_, node)| {
+                    Self::collect_fields(&Self::resolve(node, sources, 0), sources, fields)
+                }),
+                Yaml::Array(list) => list.iter().for_each(|node| {
+                    Self::collect_fields(&Self::resolve(node, sources, 0), sources, fields)
+                }),
+                _ => {}
+            }
+        }
+
+        if let Yaml::Hash(hash) = &yaml["fields"] {
+            for (name, node) in hash {
+                let name = match name.as_str() {
+                    Some(name) => name.to_lowercase(),
+                    None => continue,
+                };
+                let node = match node {
+                    Yaml::Boolean(false) => continue, // removed by an extending blueprint
+                    Yaml::Boolean(true) | Yaml::Null => {
+                        Self::resolve(&Yaml::String(format!("fields/{}", name)), sources, 0)
+                    }
+                    _ => Self::resolve(node, sources, 0),
+                };
+
+                if node["type"].as_str() == Some("group") {
+                    Self::collect_fields(&node, sources, fields);
+                    continue;
+                }
+
+                let field = FieldBlueprint::from_yaml(&name, &node);
+                if !field.kind.is_presentational() {
+                    fields.insert(name, field);
+                }
+            }
+        }
+    }
+}
+
+impl FieldBlueprint {
+    #[cfg(feature = "kirby")]
+    pub fn from_yaml(name: &str, yaml: &Yaml) -> Self {
+        // kirby uses the name of the field as type if none is set
+        let kind = FieldKind::from(yaml["type"].as_str().unwrap_or(name));
+
+        let validate = match &yaml["validate"] {
+            Yaml::String(rule) => vec![rule.to_string()],
+            Yaml::Array(rules) => rules.iter().filter_map(Self::scalar).collect(),
+            Yaml::Hash(rules) => rules
+                .iter()
+                .filter_map(|(rule, _)| rule.as_str().map(|r| r.to_string()))
+                .collect(),
+            _ => vec![],
+        };
+
+        Self {
+            name: name.to_string(),
+            kind,
+            label: Self::scalar(&yaml["label"]),
+            default: match &yaml["default"] {
+                Yaml::Array(list) => Some(
+                    list.iter()
+                        .filter_map(Self::scalar)
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                ),
+                other => Self::scalar(other),
+            },
+            options: Self::options(&yaml["options"]),
+            required: yaml["required"].as_bool().unwrap_or(false),
+            min: Self::number(&yaml["min"]),
+            max: Self::number(&yaml["max"]),
+            min_length: Self::number(&yaml["minlength"]).map(|n| n as usize),
+            max_length: Self::number(&yaml["maxlength"]).map(|n| n as usize),
+            pattern: Self::scalar(&yaml["pattern"]),
+            validate,
+            translate: yaml["translate"].as_bool().unwrap_or(true),
+        }
+    }
+
+    #[cfg(feature = "kirby")]
+    fn options(yaml: &Yaml) -> Vec<String> {
+        match yaml {
+            // options: [a, b] or options: [{value: a, text: A}]
+            Yaml::Array(list) => list
+                .iter()
+                .filter_map(|option| match option {
+                    Yaml::Hash(_) => Self::scalar(&option["value"]),
+                    _ => Self::scalar(option),
+                })
+                .collect(),
+            // options: {a: A, b: B} unless it is a query or api definition
+            Yaml::Hash(hash) => match hash.get(&Yaml::String("type".to_string())) {
+                Some(_) => vec![],
+                None => hash
+                    .iter()
+                    .filter_map(|(key, _)| Self::scalar(key))
+                    .collect(),
+            },
+            _ => vec![],
+        }
+    }
+
+    #[cfg(feature = "kirby")]
+    fn scalar(yaml: &Yaml) -> Option<String> {
+        match yaml {
+            Yaml::String(s) | Yaml::Real(s) => Some(s.to_string()),
+            Yaml::Integer(i) => Some(i.to_string()),
+            Yaml::Boolean(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "kirby")]
+    fn number(yaml: &Yaml) -> Option<f64> {
+        match yaml {
+            Yaml::Integer(i) => Some(*i as f64),
+            Yaml::Real(s) | Yaml::String(s) => s.parse::<f64>().ok(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "kirby")]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn yaml(text: &str) -> Yaml {
+        YamlLoader::load_from_str(text).unwrap().remove(0)
+    }
+
+    #[test]
+    fn it_parses_fields_in_sections_and_groups() {
+        let post = yaml(
+            "title: Post
+columns:
+  main:
+    sections:
+      content:
+        type: fields
+        fields:
+          text:
+            type: textarea
+            required: true
+            maxlength: 500
+          meta:
+            type: group
+            fields:
+              category:
+                type: select
+                options:
+                  news: News
+                  rant: Rant
+              date:
+                type: date
+                default: today
+          line: true",
+        );
+        let blueprint = Blueprint::from_yaml("pages/post", &post, &BTreeMap::new());
+        assert_eq!(blueprint.title, "Post");
+        assert_eq!(blueprint.fields.len(), 3);
+
+        let text = blueprint.field("text").unwrap();
+        assert_eq!(text.kind, FieldKind::Textarea);
+        assert!(text.required);
+        assert_eq!(text.max_length, Some(500));
+
+        let category = blueprint.field("category").unwrap();
+        assert_eq!(category.kind, FieldKind::Select);
+        assert_eq!(category.options, vec!["news", "rant"]);
+
+        assert_eq!(
+            blueprint.field("date").unwrap().default,
+            Some("today".to_string())
+        );
+    }
+
+    #[test]
+    fn it_resolves_extends() {
+        let mut sources = BTreeMap::new();
+        sources.insert(
+            "pages/default".to_string(),
+            yaml("title: Default\nfields:\n  text:\n    type: textarea\n  tags: true"),
+        );
+        sources.insert(
+            "fields/author".to_string(),
+            yaml("type: users\nmax: 1\nrequired: true"),
+        );
+
+        let post = yaml(
+            "extends: pages/default
+title: Post
+fields:
+  author: fields/author
+  tags: false
+  rating:
+    extends: fields/author
+    type: range
+    max: 5",
+        );
+        let blueprint = Blueprint::from_yaml("pages/post", &post, &sources);
+        assert_eq!(blueprint.title, "Post");
+        assert_eq!(blueprint.fields.len(), 3);
+        assert_eq!(blueprint.field("text").unwrap().kind, FieldKind::Textarea);
+        assert_eq!(blueprint.field("author").unwrap().kind, FieldKind::Users);
+        assert_eq!(blueprint.field("author").unwrap().max, Some(1.0));
+        assert_eq!(blueprint.field("rating").unwrap().kind, FieldKind::Range);
+        assert_eq!(blueprint.field("rating").unwrap().max, Some(5.0));
+        assert!(blueprint.field("rating").unwrap().required);
+        assert!(blueprint.field("tags").is_none());
+    }
+
+    #[test]
+    fn it_uses_the_name_as_kind() {
+        let field = FieldBlueprint::from_yaml("email", &yaml("label: Mail"));
+        assert_eq!(field.kind, FieldKind::Email);
+        assert_eq!(field.label, Some("Mail".to_string()));
+    }
+}
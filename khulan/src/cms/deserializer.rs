@@ -0,0 +1,681 @@
+use crate::cms::content::Content;
+use crate::cms::field::Field;
+use crate::cms::model::Model;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+#[cfg(feature = "kirby")]
+use yaml_rust::{Yaml, YamlLoader};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeserializeError {
+    pub file: String,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl Display for DeserializeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.field {
+            Some(field) => write!(
+                f,
+                "Failed to deserialize field '{}' of '{}': {}",
+                field, self.file, self.message
+            ),
+            None => write!(f, "Failed to deserialize '{}': {}", self.file, self.message),
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        DeserializeError {
+            file: "".to_string(),
+            field: None,
+            message: msg.to_string(),
+        }
+    }
+
+    /// names the missing field, not the last field of the content
+    fn missing_field(field: &'static str) -> Self {
+        DeserializeError {
+            file: "".to_string(),
+            field: Some(field.to_string()),
+            message: format!("missing field `{}`", field),
+        }
+    }
+}
+
+impl DeserializeError {
+    fn locate(mut self, file: &str) -> Self {
+        if self.file.is_empty() {
+            self.file = file.to_string();
+        }
+        self
+    }
+}
+
+impl Content {
+    /// Map the fields of the content onto a typed struct. `file` is only used in errors.
+    pub fn deserialize<T: DeserializeOwned>(&self, file: &str) -> Result<T, DeserializeError> {
+        let fields = self
+            .fields
+            .values()
+            .map(|field| (field.name(), Cow::Borrowed(field)))
+            .collect();
+        ContentDeserializer { file, fields }.deserialize()
+    }
+}
+
+impl Model {
+    /// Map the content of the model onto a typed struct. Besides the content fields,
+    /// `path`, `language`, `template` and `num` are available unless the content overrides them.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, DeserializeError> {
+        let root = self.root();
+        let mut fields: Vec<(&str, Cow<Field>)> = self
+            .content()
+            .fields
+            .values()
+            .map(|field| (field.name(), Cow::Borrowed(field)))
+            .collect();
+
+        let num = self.num().map(|num| num.to_string()).unwrap_or_default();
+        for (name, value) in [
+            ("path", self.path()),
+            ("language", self.language().to_string()),
+            ("template", self.template().to_string()),
+            ("num", num),
+        ] {
+            if !self.content().fields.contains_key(name) {
+                fields.push((name, Cow::Owned(Field::new(name, Some(&value)))));
+            }
+        }
+
+        ContentDeserializer {
+            file: &root,
+            fields,
+        }
+        .deserialize()
+    }
+}
+
+struct ContentDeserializer<'a> {
+    file: &'a str,
+    fields: Vec<(&'a str, Cow<'a, Field>)>,
+}
+
+impl<'a> ContentDeserializer<'a> {
+    fn deserialize<T: DeserializeOwned>(self) -> Result<T, DeserializeError> {
+        let file = self.file;
+        let mut map = ContentMap {
+            fields: self.fields.into_iter(),
+            current: None,
+        };
+        // errors of values already name their field in `next_value_seed`
+        T::deserialize(de::value::MapAccessDeserializer::new(&mut map)).map_err(|e| e.locate(file))
+    }
+}
+
+struct ContentMap<'a> {
+    fields: std::vec::IntoIter<(&'a str, Cow<'a, Field>)>,
+    current: Option<(String, Cow<'a, Field>)>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for &mut ContentMap<'a> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.fields.next() {
+            Some((name, field)) => {
+                self.current = Some((name.to_string(), field));
+                seed.deserialize(name.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (name, field) = self
+            .current
+            .as_ref()
+            .ok_or_else(|| de::Error::custom("value without a field"))?;
+        seed.deserialize(ValueDeserializer::new(
+            field.value(),
+            field.kind().is_structured(),
+        ))
+        .map_err(|e| DeserializeError {
+            field: Some(name.clone()),
+            ..e
+        })
+    }
+}
+
+/// Deserializer for a single value of a field as stored in kirby txt files
+pub struct ValueDeserializer<'a> {
+    value: &'a str,
+    structured: bool,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    pub fn new(value: &'a str, structured: bool) -> Self {
+        Self {
+            value: value.trim(),
+            structured,
+        }
+    }
+
+    fn is_yaml(&self) -> bool {
+        self.structured || self.value.starts_with("- ") || self.value.starts_with("-\n")
+    }
+
+    fn parse<T: std::str::FromStr>(&self, expected: &str) -> Result<T, DeserializeError>
+    where
+        T::Err: Display,
+    {
+        self.value.parse::<T>().map_err(|e| {
+            de::Error::custom(format!(
+                "'{}' is not a valid {}: {}",
+                self.value, expected, e
+            ))
+        })
+    }
+
+    #[cfg(feature = "kirby")]
+    fn yaml(&self) -> Result<Yaml, DeserializeError> {
+        match YamlLoader::load_from_str(self.value) {
+            Ok(mut docs) if !docs.is_empty() => Ok(docs.remove(0)),
+            Ok(_) => Ok(Yaml::Null),
+            Err(e) => Err(de::Error::custom(format!("invalid yaml: {}", e))),
+        }
+    }
+
+    #[cfg(not(feature = "kirby"))]
+    fn yaml(&self) -> Result<(), DeserializeError> {
+        Err(de::Error::custom("yaml values require the kirby feature"))
+    }
+}
+
+/// kirby stores toggles as `true`/`false` but older content often uses other words
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" | "" => Some(false),
+        _ => None,
+    }
+}
+
+/// parse kirby dates like `2024-10-17`, `2024-10-17 12:00`, `2024-10-17 12:00:00` or unix seconds
+pub fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    for format in [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(datetime);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+    value
+        .parse::<i64>()
+        .ok()
+        .and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0))
+        .map(|datetime| datetime.naive_utc())
+}
+
+/// Use with `#[serde(deserialize_with = "khulan::cms::deserializer::datetime")]`
+/// for `NaiveDateTime` fields since kirby does not store dates in ISO 8601.
+pub fn datetime<'de, D: de::Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+    let value: String = de::Deserialize::deserialize(deserializer)?;
+    parse_datetime(&value)
+        .ok_or_else(|| de::Error::custom(format!("'{}' is not a valid date", value)))
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $ty:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse::<$ty>(stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.is_yaml() {
+            #[cfg(feature = "kirby")]
+            return YamlDeserializer(&self.yaml()?).deserialize_any(visitor);
+        }
+        visitor.visit_str(self.value)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match parse_bool(self.value) {
+            Some(b) => visitor.visit_bool(b),
+            None => Err(de::Error::custom(format!(
+                "'{}' is not a valid bool",
+                self.value
+            ))),
+        }
+    }
+
+    deserialize_parsed!(
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_char => visit_char: char
+    );
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.value)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.value)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bytes(self.value.as_bytes())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bytes(self.value.as_bytes())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.value.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.is_yaml() {
+            #[cfg(feature = "kirby")]
+            return YamlDeserializer(&self.yaml()?).deserialize_seq(visitor);
+        }
+
+        // comma separated lists like tags
+        let items = self
+            .value
+            .split(',')
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .map(|item| ValueDeserializer::new(item, false));
+        let mut seq = SeqDeserializer::new(items);
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        #[cfg(feature = "kirby")]
+        return YamlDeserializer(&self.yaml()?).deserialize_map(visitor);
+        #[cfg(not(feature = "kirby"))]
+        {
+            self.yaml()?;
+            visitor.visit_unit()
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.value)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
+impl<'de, 'a> IntoDeserializer<'de, DeserializeError> for ValueDeserializer<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Deserializer for yaml structures like structure, blocks, pages or files fields
+#[cfg(feature = "kirby")]
+pub struct YamlDeserializer<'a>(pub &'a Yaml);
+
+#[cfg(feature = "kirby")]
+impl<'a> YamlDeserializer<'a> {
+    fn scalar(&self) -> Option<Cow<'a, str>> {
+        match self.0 {
+            Yaml::String(s) | Yaml::Real(s) => Some(Cow::Borrowed(s)),
+            Yaml::Integer(i) => Some(Cow::Owned(i.to_string())),
+            Yaml::Boolean(b) => Some(Cow::Owned(b.to_string())),
+            _ => None,
+        }
+    }
+
+    fn mismatch(&self) -> DeserializeError {
+        de::Error::custom(format!("expected a single value but found {:?}", self.0))
+    }
+}
+
+#[cfg(feature = "kirby")]
+macro_rules! deserialize_scalar {
+    ($($method:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match (self.scalar(), self.0) {
+                    (Some(value), _) => ValueDeserializer::new(&value, false).$method(visitor),
+                    (None, Yaml::Null) => ValueDeserializer::new("", false).$method(visitor),
+                    _ => Err(self.mismatch()),
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "kirby")]
+impl<'de, 'a> de::Deserializer<'de> for YamlDeserializer<'a> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Yaml::String(s) => visitor.visit_str(s),
+            Yaml::Integer(i) => visitor.visit_i64(*i),
+            Yaml::Real(s) => visitor.visit_f64(s.parse::<f64>().map_err(de::Error::custom)?),
+            Yaml::Boolean(b) => visitor.visit_bool(*b),
+            Yaml::Array(list) => {
+                let mut seq = SeqDeserializer::new(list.iter().map(YamlDeserializer));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Yaml::Hash(hash) => {
+                let mut map = MapDeserializer::new(
+                    hash.iter()
+                        .map(|(k, v)| (YamlDeserializer(k), YamlDeserializer(v))),
+                );
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            Yaml::Null | Yaml::BadValue => visitor.visit_unit(),
+            Yaml::Alias(_) => Err(de::Error::custom("yaml aliases are not supported")),
+        }
+    }
+
+    deserialize_scalar!(
+        deserialize_bool,
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_f32,
+        deserialize_f64,
+        deserialize_char,
+        deserialize_str,
+        deserialize_string
+    );
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Yaml::Null | Yaml::BadValue => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.scalar() {
+            // a single value where a list is expected, like `tags: rust, kirby` in a structure
+            Some(value) => ValueDeserializer::new(&value, false).deserialize_seq(visitor),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.scalar() {
+            Some(value) => visitor.visit_enum(value.into_deserializer()),
+            None => Err(de::Error::custom("only unit variants are supported")),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf unit unit_struct tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(feature = "kirby")]
+impl<'de, 'a> IntoDeserializer<'de, DeserializeError> for YamlDeserializer<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::blueprint::FieldKind;
+    use crate::cms::model::{ModelBuilder, ModelKind};
+    use maplit::hashmap;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Post {
+        title: String,
+        path: String,
+        published: bool,
+        rating: Option<u8>,
+        tags: Vec<String>,
+        #[serde(deserialize_with = "datetime")]
+        date: NaiveDateTime,
+        day: NaiveDate,
+        #[serde(default)]
+        missing: Option<String>,
+    }
+
+    fn model(fields: Vec<(&str, &str)>) -> Model {
+        let content = Content::new(Some(
+            &fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), Field::new(name, Some(value))))
+                .collect(),
+        ));
+        ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("blog/whales")
+            .root("/content/blog/1_whales/post.txt")
+            .content(&content)
+            .build()
+    }
+
+    #[test]
+    fn it_deserializes_a_model() {
+        let post: Post = model(vec![
+            ("title", "Whales"),
+            ("published", "true"),
+            ("rating", "4"),
+            ("tags", "sea, mammals"),
+            ("date", "2024-10-17 12:30"),
+            ("day", "2024-10-17"),
+        ])
+        .deserialize()
+        .unwrap();
+
+        assert_eq!(post.title, "Whales");
+        assert_eq!(post.path, "blog/whales");
+        assert!(post.published);
+        assert_eq!(post.rating, Some(4));
+        assert_eq!(post.tags, vec!["sea", "mammals"]);
+        assert_eq!(post.date.to_string(), "2024-10-17 12:30:00");
+        assert_eq!(post.day.to_string(), "2024-10-17");
+        assert_eq!(post.missing, None);
+    }
+
+    #[test]
+    fn it_names_file_and_field_in_errors() {
+        let error = model(vec![
+            ("title", "Whales"),
+            ("published", "true"),
+            ("rating", "many"),
+            ("tags", ""),
+            ("date", "2024-10-17"),
+            ("day", "2024-10-17"),
+        ])
+        .deserialize::<Post>()
+        .unwrap_err();
+
+        assert_eq!(error.file, "/content/blog/1_whales/post.txt");
+        assert_eq!(error.field, Some("rating".to_string()));
+        assert!(error.to_string().contains("'many' is not a valid u8"));
+    }
+
+    #[test]
+    fn it_names_the_missing_field_in_errors() {
+        let error = model(vec![
+            ("title", "Whales"),
+            ("published", "true"),
+            ("tags", ""),
+            ("date", "2024-10-17"),
+            ("zzz", "last"),
+        ])
+        .deserialize::<Post>()
+        .unwrap_err();
+
+        assert_eq!(error.field, Some("day".to_string()));
+        assert_eq!(
+            error.to_string(),
+            "Failed to deserialize field 'day' of '/content/blog/1_whales/post.txt': missing field `day`"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "kirby")]
+    fn it_deserializes_yaml_structures() {
+        #[derive(Debug, Deserialize)]
+        struct Link {
+            label: String,
+            url: String,
+            external: bool,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Footer {
+            links: Vec<Link>,
+            related: Vec<String>,
+        }
+
+        let mut links = Field::new(
+            "links",
+            Some("- \n  label: Kirby\n  url: https://getkirby.com\n  external: true"),
+        );
+        links.set_kind(&FieldKind::Structure);
+        let content = Content::new(Some(&hashmap! {
+            "links".to_string() => links,
+            "related".to_string() => Field::new("related", Some("- page://abc\n- page://def")),
+        }));
+
+        let footer: Footer = content.deserialize("site.txt").unwrap();
+        assert_eq!(footer.links.len(), 1);
+        assert_eq!(footer.links[0].label, "Kirby");
+        assert_eq!(footer.links[0].url, "https://getkirby.com");
+        assert!(footer.links[0].external);
+        assert_eq!(footer.related, vec!["page://abc", "page://def"]);
+    }
+}
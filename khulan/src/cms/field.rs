@@ -1,10 +1,13 @@
+use crate::cms::blueprint::FieldKind;
+use crate::cms::deserializer::parse_datetime;
+use chrono::NaiveDateTime;
 use rocket::serde::Serialize;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, Serialize, Default, Eq, PartialEq)]
 pub struct Field {
     pub name: String,
-    // kind: String,
+    pub kind: FieldKind,
     pub value: String,
 }
 
@@ -13,6 +16,7 @@ impl Field {
     pub fn new(name: &str, value: Option<&str>) -> Self {
         Self {
             name: name.to_string().trim().to_lowercase(),
+            kind: FieldKind::None,
             value: value.unwrap_or("").to_string(),
         }
     }
@@ -25,6 +29,10 @@ impl Field {
         &self.value
     }
 
+    pub fn kind(&self) -> &FieldKind {
+        &self.kind
+    }
+
     pub fn set_name(&mut self, name: &str) {
         self.name = name.to_string();
     }
@@ -33,6 +41,10 @@ impl Field {
         self.value = value.to_string();
     }
 
+    pub fn set_kind(&mut self, kind: &FieldKind) {
+        self.kind = kind.clone();
+    }
+
     pub fn to_int(&self) -> i32 {
         self.value.parse::<i32>().unwrap()
     }
@@ -53,11 +65,16 @@ impl Field {
         self.value.split(",").map(|s| s.to_string()).collect()
     }
 
-    pub fn to_systemtime(&self) -> SystemTime {
-        match self.value.parse::<u64>() {
-            Ok(seconds) => UNIX_EPOCH + std::time::Duration::from_secs(seconds),
-            Err(_) => SystemTime::now(), // Fallback in case of parse failure
-        }
+    /// kirby dates like `2024-10-17 12:00` or unix seconds, `None` if it is no date
+    pub fn to_datetime(&self) -> Option<NaiveDateTime> {
+        parse_datetime(&self.value)
+    }
+
+    /// the date as utc, since kirby stores dates without timezone
+    pub fn to_systemtime(&self) -> Option<SystemTime> {
+        let datetime = self.to_datetime()?.and_utc();
+        let seconds = u64::try_from(datetime.timestamp()).ok()?;
+        Some(UNIX_EPOCH + std::time::Duration::from_secs(seconds))
     }
 }
 
@@ -80,4 +97,26 @@ mod tests_field {
         field.value = "Hello, World!".to_string();
         assert_eq!(field.value(), "Hello, World!");
     }
+
+    #[test]
+    fn it_parses_dates() {
+        let field = Field::new("date", Some("2024-10-17 12:30"));
+        assert_eq!(
+            field.to_datetime().unwrap().to_string(),
+            "2024-10-17 12:30:00"
+        );
+        assert_eq!(
+            Field::new("date", Some("86400")).to_systemtime(),
+            Some(UNIX_EPOCH + std::time::Duration::from_secs(86400))
+        );
+        assert_eq!(Field::new("date", Some("tomorrow")).to_systemtime(), None);
+    }
+
+    #[test]
+    fn it_sets_kind() {
+        let mut field = Field::new("title", None);
+        assert_eq!(*field.kind(), FieldKind::None);
+        field.set_kind(&FieldKind::Text);
+        assert_eq!(field.kind().as_str(), "text");
+    }
 }
@@ -0,0 +1,287 @@
+use crate::cms::model::Model;
+use crate::cms::pages::{natural_cmp, Pages};
+use crate::cms::site::Site;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// the files of a page or the site, with the filters and sorting of pages
+pub type Files<'a> = Pages<'a>;
+
+/// The type of a file like in kirby, by its extension or else its MIME type
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum FileType {
+    Image,
+    Document,
+    Video,
+    Audio,
+    Code,
+    Archive,
+    Other,
+}
+
+impl FileType {
+    pub fn from_extension(extension: &str) -> Self {
+        match extension.to_lowercase().as_str() {
+            "ai" | "avif" | "bmp" | "gif" | "eps" | "ico" | "j2k" | "jp2" | "jpeg" | "jpg"
+            | "jpe" | "png" | "ps" | "psd" | "svg" | "tif" | "tiff" | "webp" => FileType::Image,
+            "csv" | "doc" | "docx" | "dotx" | "indd" | "md" | "mdown" | "pdf" | "pptx" | "rtf"
+            | "txt" | "xl" | "xls" | "xlsx" | "xltx" | "odt" | "ods" | "odp" => FileType::Document,
+            "avi" | "flv" | "m4v" | "mov" | "movie" | "mpe" | "mpg" | "mp4" | "ogg" | "ogv"
+            | "swf" | "webm" => FileType::Video,
+            "aif" | "aiff" | "flac" | "m4a" | "midi" | "mp3" | "wav" => FileType::Audio,
+            "css" | "js" | "json" | "java" | "htm" | "html" | "php" | "rb" | "py" | "rs"
+            | "scss" | "xml" | "yaml" | "yml" => FileType::Code,
+            "gz" | "gzip" | "tar" | "tgz" | "zip" | "7z" | "rar" => FileType::Archive,
+            extension => Self::from_mime(
+                mime_guess::from_ext(extension)
+                    .first_raw()
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+
+    pub fn from_mime(mime: &str) -> Self {
+        match mime.split_once('/') {
+            Some(("image", _)) => FileType::Image,
+            Some(("video", _)) => FileType::Video,
+            Some(("audio", _)) => FileType::Audio,
+            Some(("text", _)) | Some(("application", "pdf")) => FileType::Document,
+            Some(("application", "zip")) | Some(("application", "gzip")) => FileType::Archive,
+            _ => FileType::Other,
+        }
+    }
+}
+
+impl Model {
+    /// the name of the file without its metadata suffix, like `whale.jpg`
+    pub fn filename(&self) -> Option<String> {
+        self.file_root().and_then(|file| {
+            Path::new(&file)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+    }
+
+    pub fn extension(&self) -> Option<String> {
+        self.file_root().and_then(|file| {
+            Path::new(&file)
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+        })
+    }
+
+    pub fn mime(&self) -> Option<String> {
+        self.file_root().map(|file| {
+            mime_guess::from_path(file)
+                .first_or_octet_stream()
+                .to_string()
+        })
+    }
+
+    pub fn file_type(&self) -> Option<FileType> {
+        self.extension()
+            .map(|extension| FileType::from_extension(&extension))
+    }
+
+    /// Changes with the file like in kirby, from the path and the time it was modified.
+    /// The time is read from the file, since the model may be loaded from its metadata.
+    pub fn media_hash(&self) -> Option<String> {
+        let file = self.file_root()?;
+        let modified = fs::metadata(&file)
+            .and_then(|metadata| metadata.modified())
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs();
+        Some(format!(
+            "{:08x}-{}",
+            crc32fast::hash(file.as_bytes()),
+            modified
+        ))
+    }
+
+    /// the folder of the file and its thumbs under `/media`, like `pages/blog/whales/<hash>`
+    pub fn media_folder(&self) -> Option<String> {
+        let parent = match self.id().trim_matches('/') {
+            "" => "site".to_string(),
+            id => format!("pages/{}", id),
+        };
+        Some(format!("{}/{}", parent, self.media_hash()?))
+    }
+
+    /// the url of the file like `/media/pages/blog/whales/<hash>/whale.jpg` on the site url
+    pub fn media_url(&self, site: &Site) -> Option<String> {
+        Some(format!(
+            "{}/media/{}/{}",
+            site.url().as_str().trim_end_matches('/'),
+            self.media_folder()?,
+            self.filename()?
+        ))
+    }
+
+    /// the page of the file in the language of the file, none for files of the site
+    pub fn page<'a>(&self, site: &'a Site) -> Option<&'a Model> {
+        if !self.is_file() {
+            return None;
+        }
+        site.index
+            .key(self.language(), self.id())
+            .and_then(|key| site.models.get(key))
+    }
+
+    /// the files in the folder of the page or the site, by their `sort` field and then name
+    pub fn files<'a>(&self, site: &'a Site) -> Files<'a> {
+        let mut files: Vec<&Model> = site
+            .index
+            .files(self.language(), self.id())
+            .filter_map(|key| site.models.get(key))
+            .collect();
+        files.sort_by(|a, b| {
+            (a.num().is_none(), a.num())
+                .cmp(&(b.num().is_none(), b.num()))
+                .then_with(|| {
+                    natural_cmp(
+                        &a.filename().unwrap_or_default(),
+                        &b.filename().unwrap_or_default(),
+                    )
+                })
+        });
+        Files::new(files)
+    }
+
+    pub fn images<'a>(&self, site: &'a Site) -> Files<'a> {
+        self.files_of_type(site, FileType::Image)
+    }
+
+    pub fn documents<'a>(&self, site: &'a Site) -> Files<'a> {
+        self.files_of_type(site, FileType::Document)
+    }
+
+    pub fn videos<'a>(&self, site: &'a Site) -> Files<'a> {
+        self.files_of_type(site, FileType::Video)
+    }
+
+    pub fn audio<'a>(&self, site: &'a Site) -> Files<'a> {
+        self.files_of_type(site, FileType::Audio)
+    }
+
+    fn files_of_type<'a>(&self, site: &'a Site, file_type: FileType) -> Files<'a> {
+        self.files(site)
+            .filter(|file| file.file_type() == Some(file_type))
+    }
+
+    /// the file by its name like `whale.jpg`, like `$page->file('whale.jpg')` in kirby
+    pub fn file_named<'a>(&self, site: &'a Site, name: &str) -> Option<&'a Model> {
+        self.files(site)
+            .iter()
+            .find(|file| file.filename().as_deref() == Some(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::content::Content;
+    use crate::cms::field::Field;
+    use crate::cms::language::Languages;
+    use crate::cms::model::{ModelBuilder, ModelKind};
+    use crate::cms::site::{Resolved, SiteBuilder};
+
+    fn file(root: &str, sort: Option<&str>) -> Model {
+        let mut builder = ModelBuilder::new();
+        builder
+            .kind(&ModelKind::File)
+            .path("blog/whales")
+            .language("en")
+            .root(root);
+        if let Some(sort) = sort {
+            builder.content(&Content::new(Some(
+                &[("sort".to_string(), Field::new("sort", Some(sort)))]
+                    .into_iter()
+                    .collect(),
+            )));
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn it_detects_file_types() {
+        assert_eq!(FileType::from_extension("JPG"), FileType::Image);
+        assert_eq!(FileType::from_extension("pdf"), FileType::Document);
+        assert_eq!(FileType::from_extension("webm"), FileType::Video);
+        assert_eq!(FileType::from_extension("zip"), FileType::Archive);
+        assert_eq!(FileType::from_extension("heic"), FileType::Image);
+        assert_eq!(FileType::from_extension("unknown"), FileType::Other);
+    }
+
+    #[test]
+    fn it_attaches_files_to_pages() {
+        let mut site = SiteBuilder::new().build();
+        let page = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("blog/whales")
+            .language("en")
+            .build();
+        site.insert(page.clone());
+        site.insert(file("/content/blog/1_whales/whale.jpg.en.txt", Some("2")));
+        site.insert(file("/content/blog/1_whales/dolphin.png.en.txt", Some("1")));
+        site.insert(file("/content/blog/1_whales/report.pdf", None));
+        site.insert(file("/content/blog/1_whales/clip.mp4", None));
+
+        let names: Vec<String> = page
+            .files(&site)
+            .iter()
+            .filter_map(|file| file.filename())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["dolphin.png", "whale.jpg", "clip.mp4", "report.pdf"]
+        );
+        assert_eq!(page.images(&site).len(), 2);
+        assert_eq!(page.documents(&site).len(), 1);
+        assert_eq!(page.videos(&site).len(), 1);
+
+        let whale = page.file_named(&site, "whale.jpg").unwrap();
+        assert_eq!(
+            whale.file_root().unwrap(),
+            "/content/blog/1_whales/whale.jpg"
+        );
+        assert_eq!(whale.mime().unwrap(), "image/jpeg");
+        assert_eq!(whale.page(&site), Some(&page));
+        assert_eq!(page.page(&site), None);
+    }
+
+    #[test]
+    fn it_resolves_media_urls() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path().join("blog/1_whales");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("whale.jpg"), "jpg").unwrap();
+
+        let mut site = SiteBuilder::new().build();
+        site.languages = Languages::from_codes(&["en"]);
+        site.insert(file(&folder.join("whale.jpg").to_string_lossy(), None));
+        site.insert(file(&folder.join("post.en.txt").to_string_lossy(), None));
+
+        let whale = site
+            .models
+            .values()
+            .find(|m| m.filename().as_deref() == Some("whale.jpg"));
+        let url = whale.unwrap().media_url(&site).unwrap();
+        let path = url.trim_start_matches("http://localhost:8000/media/");
+        assert!(path.starts_with("pages/blog/whales/"));
+        assert!(matches!(site.resolve_media(path), Some(Resolved::Page(_))));
+        assert!(matches!(
+            site.resolve_media("pages/blog/whales/0-0/whale.jpg"),
+            Some(Resolved::Redirect(_))
+        ));
+        assert_eq!(
+            site.resolve_media("pages/blog/whales/0-0/post.en.txt"),
+            None
+        );
+        assert_eq!(site.resolve_media("pages/0-0/whale.jpg"), None);
+    }
+}
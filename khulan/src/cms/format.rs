@@ -0,0 +1,647 @@
+use crate::cms::language::plural_category;
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateStyle {
+    /// like `17.10.24`
+    Short,
+    /// like `17.10.2024`
+    Medium,
+    /// like `17. Oktober 2024`
+    Long,
+    /// like `Donnerstag, 17. Oktober 2024`
+    Full,
+}
+
+impl From<&str> for DateStyle {
+    fn from(style: &str) -> Self {
+        match style.trim().to_lowercase().as_str() {
+            "short" => DateStyle::Short,
+            "long" => DateStyle::Long,
+            "full" => DateStyle::Full,
+            _ => DateStyle::Medium,
+        }
+    }
+}
+
+/// The names and patterns to format dates, times and numbers in a language.
+/// Patterns use the `strftime` syntax of chrono, names are replaced by the locale.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Locale {
+    pub code: &'static str,
+    pub months: [&'static str; 12],
+    pub months_short: [&'static str; 12],
+    /// from monday to sunday
+    pub weekdays: [&'static str; 7],
+    pub weekdays_short: [&'static str; 7],
+    /// short, medium, long and full
+    pub dates: [&'static str; 4],
+    pub time: &'static str,
+    pub decimal: &'static str,
+    pub group: &'static str,
+    /// with `{n}` for the number and `{c}` for the currency symbol
+    pub currency: &'static str,
+    /// like `{} ago`, `in {}` and `just now`
+    pub past: &'static str,
+    pub future: &'static str,
+    pub now: &'static str,
+    /// singular and plural of second, minute, hour, day, week, month and year
+    pub units: [(&'static str, &'static str); 7],
+}
+
+static LOCALES: [Locale; 7] = [
+    Locale {
+        code: "en",
+        months: [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ],
+        months_short: [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ],
+        weekdays: [
+            "Monday",
+            "Tuesday",
+            "Wednesday",
+            "Thursday",
+            "Friday",
+            "Saturday",
+            "Sunday",
+        ],
+        weekdays_short: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+        dates: ["%-m/%-d/%y", "%b %-d, %Y", "%B %-d, %Y", "%A, %B %-d, %Y"],
+        time: "%-I:%M %p",
+        decimal: ".",
+        group: ",",
+        currency: "{c}{n}",
+        past: "{} ago",
+        future: "in {}",
+        now: "just now",
+        units: [
+            ("second", "seconds"),
+            ("minute", "minutes"),
+            ("hour", "hours"),
+            ("day", "days"),
+            ("week", "weeks"),
+            ("month", "months"),
+            ("year", "years"),
+        ],
+    },
+    Locale {
+        code: "de",
+        months: [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ],
+        months_short: [
+            "Jan.", "Feb.", "März", "Apr.", "Mai", "Juni", "Juli", "Aug.", "Sept.", "Okt.", "Nov.",
+            "Dez.",
+        ],
+        weekdays: [
+            "Montag",
+            "Dienstag",
+            "Mittwoch",
+            "Donnerstag",
+            "Freitag",
+            "Samstag",
+            "Sonntag",
+        ],
+        weekdays_short: ["Mo.", "Di.", "Mi.", "Do.", "Fr.", "Sa.", "So."],
+        dates: ["%d.%m.%y", "%d.%m.%Y", "%-d. %B %Y", "%A, %-d. %B %Y"],
+        time: "%H:%M",
+        decimal: ",",
+        group: ".",
+        currency: "{n}\u{a0}{c}",
+        past: "vor {}",
+        future: "in {}",
+        now: "gerade eben",
+        units: [
+            ("Sekunde", "Sekunden"),
+            ("Minute", "Minuten"),
+            ("Stunde", "Stunden"),
+            ("Tag", "Tagen"),
+            ("Woche", "Wochen"),
+            ("Monat", "Monaten"),
+            ("Jahr", "Jahren"),
+        ],
+    },
+    Locale {
+        code: "fr",
+        months: [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+        months_short: [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+            "nov.", "déc.",
+        ],
+        weekdays: [
+            "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+        ],
+        weekdays_short: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+        dates: ["%d/%m/%Y", "%-d %b %Y", "%-d %B %Y", "%A %-d %B %Y"],
+        time: "%H:%M",
+        decimal: ",",
+        group: "\u{202f}",
+        currency: "{n}\u{a0}{c}",
+        past: "il y a {}",
+        future: "dans {}",
+        now: "à l’instant",
+        units: [
+            ("seconde", "secondes"),
+            ("minute", "minutes"),
+            ("heure", "heures"),
+            ("jour", "jours"),
+            ("semaine", "semaines"),
+            ("mois", "mois"),
+            ("an", "ans"),
+        ],
+    },
+    Locale {
+        code: "es",
+        months: [
+            "enero",
+            "febrero",
+            "marzo",
+            "abril",
+            "mayo",
+            "junio",
+            "julio",
+            "agosto",
+            "septiembre",
+            "octubre",
+            "noviembre",
+            "diciembre",
+        ],
+        months_short: [
+            "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic",
+        ],
+        weekdays: [
+            "lunes",
+            "martes",
+            "miércoles",
+            "jueves",
+            "viernes",
+            "sábado",
+            "domingo",
+        ],
+        weekdays_short: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+        dates: [
+            "%-d/%-m/%y",
+            "%-d %b %Y",
+            "%-d de %B de %Y",
+            "%A, %-d de %B de %Y",
+        ],
+        time: "%H:%M",
+        decimal: ",",
+        group: ".",
+        currency: "{n}\u{a0}{c}",
+        past: "hace {}",
+        future: "dentro de {}",
+        now: "ahora mismo",
+        units: [
+            ("segundo", "segundos"),
+            ("minuto", "minutos"),
+            ("hora", "horas"),
+            ("día", "días"),
+            ("semana", "semanas"),
+            ("mes", "meses"),
+            ("año", "años"),
+        ],
+    },
+    Locale {
+        code: "it",
+        months: [
+            "gennaio",
+            "febbraio",
+            "marzo",
+            "aprile",
+            "maggio",
+            "giugno",
+            "luglio",
+            "agosto",
+            "settembre",
+            "ottobre",
+            "novembre",
+            "dicembre",
+        ],
+        months_short: [
+            "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+        ],
+        weekdays: [
+            "lunedì",
+            "martedì",
+            "mercoledì",
+            "giovedì",
+            "venerdì",
+            "sabato",
+            "domenica",
+        ],
+        weekdays_short: ["lun", "mar", "mer", "gio", "ven", "sab", "dom"],
+        dates: ["%d/%m/%y", "%-d %b %Y", "%-d %B %Y", "%A %-d %B %Y"],
+        time: "%H:%M",
+        decimal: ",",
+        group: ".",
+        currency: "{n}\u{a0}{c}",
+        past: "{} fa",
+        future: "tra {}",
+        now: "adesso",
+        units: [
+            ("secondo", "secondi"),
+            ("minuto", "minuti"),
+            ("ora", "ore"),
+            ("giorno", "giorni"),
+            ("settimana", "settimane"),
+            ("mese", "mesi"),
+            ("anno", "anni"),
+        ],
+    },
+    Locale {
+        code: "nl",
+        months: [
+            "januari",
+            "februari",
+            "maart",
+            "april",
+            "mei",
+            "juni",
+            "juli",
+            "augustus",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+        months_short: [
+            "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+        ],
+        weekdays: [
+            "maandag",
+            "dinsdag",
+            "woensdag",
+            "donderdag",
+            "vrijdag",
+            "zaterdag",
+            "zondag",
+        ],
+        weekdays_short: ["ma", "di", "wo", "do", "vr", "za", "zo"],
+        dates: ["%d-%m-%Y", "%-d %b %Y", "%-d %B %Y", "%A %-d %B %Y"],
+        time: "%H:%M",
+        decimal: ",",
+        group: ".",
+        currency: "{c}\u{a0}{n}",
+        past: "{} geleden",
+        future: "over {}",
+        now: "zojuist",
+        units: [
+            ("seconde", "seconden"),
+            ("minuut", "minuten"),
+            ("uur", "uur"),
+            ("dag", "dagen"),
+            ("week", "weken"),
+            ("maand", "maanden"),
+            ("jaar", "jaar"),
+        ],
+    },
+    Locale {
+        code: "pt",
+        months: [
+            "janeiro",
+            "fevereiro",
+            "março",
+            "abril",
+            "maio",
+            "junho",
+            "julho",
+            "agosto",
+            "setembro",
+            "outubro",
+            "novembro",
+            "dezembro",
+        ],
+        months_short: [
+            "jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez",
+        ],
+        weekdays: [
+            "segunda-feira",
+            "terça-feira",
+            "quarta-feira",
+            "quinta-feira",
+            "sexta-feira",
+            "sábado",
+            "domingo",
+        ],
+        weekdays_short: ["seg.", "ter.", "qua.", "qui.", "sex.", "sáb.", "dom."],
+        dates: [
+            "%d/%m/%Y",
+            "%-d de %b de %Y",
+            "%-d de %B de %Y",
+            "%A, %-d de %B de %Y",
+        ],
+        time: "%H:%M",
+        decimal: ",",
+        group: ".",
+        currency: "{c}\u{a0}{n}",
+        past: "há {}",
+        future: "em {}",
+        now: "agora",
+        units: [
+            ("segundo", "segundos"),
+            ("minuto", "minutos"),
+            ("hora", "horas"),
+            ("dia", "dias"),
+            ("semana", "semanas"),
+            ("mês", "meses"),
+            ("ano", "anos"),
+        ],
+    },
+];
+
+impl Locale {
+    /// the locale for a language code or locale like `de_DE.utf-8`, english if it is unknown
+    pub fn get(locale: &str) -> &'static Locale {
+        let language = locale
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        LOCALES
+            .iter()
+            .find(|l| l.code == language)
+            .unwrap_or(&LOCALES[0])
+    }
+
+    pub fn date(&self, datetime: &NaiveDateTime, style: DateStyle) -> String {
+        let pattern = match style {
+            DateStyle::Short => self.dates[0],
+            DateStyle::Medium => self.dates[1],
+            DateStyle::Long => self.dates[2],
+            DateStyle::Full => self.dates[3],
+        };
+        // the patterns of the locales are valid
+        self.format(datetime, pattern).unwrap_or_default()
+    }
+
+    pub fn time(&self, datetime: &NaiveDateTime) -> String {
+        self.format(datetime, self.time).unwrap_or_default()
+    }
+
+    pub fn datetime(&self, datetime: &NaiveDateTime, style: DateStyle) -> String {
+        format!("{} {}", self.date(datetime, style), self.time(datetime))
+    }
+
+    /// Format with a `strftime` pattern, with the names of months and weekdays
+    /// (`%B`, `%b`, `%A`, `%a`) in the language of the locale.
+    /// Fails for unknown specifiers like `%Q` and for time zones, which dates do not have.
+    pub fn format(&self, datetime: &NaiveDateTime, pattern: &str) -> Result<String, fmt::Error> {
+        let mut localized = String::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                localized.push(c);
+                continue;
+            }
+            let month = datetime.month0() as usize;
+            let weekday = datetime.weekday().num_days_from_monday() as usize;
+            let name = match chars.next() {
+                Some('B') => self.months[month].to_string(),
+                Some('b') | Some('h') => self.months_short[month].to_string(),
+                Some('A') => self.weekdays[weekday].to_string(),
+                Some('a') => self.weekdays_short[weekday].to_string(),
+                Some('p') => match datetime.hour() < 12 {
+                    true => "AM".to_string(),
+                    false => "PM".to_string(),
+                },
+                Some(other) => {
+                    localized.push('%');
+                    localized.push(other);
+                    // flags like `%-d` are followed by the specifier
+                    if matches!(other, '-' | '_' | '0') {
+                        if let Some(specifier) = chars.next() {
+                            localized.push(specifier);
+                        }
+                    }
+                    continue;
+                }
+                None => "%".to_string(),
+            };
+            // names must not be read as specifiers by chrono
+            localized.push_str(&name.replace('%', "%%"));
+        }
+        // `to_string` would panic for invalid patterns
+        let mut formatted = String::new();
+        write!(formatted, "{}", datetime.format(&localized))?;
+        Ok(formatted)
+    }
+
+    /// the time between the date and now in words, like `3 days ago` or `in 2 hours`
+    pub fn relative(&self, datetime: &NaiveDateTime, now: &NaiveDateTime) -> String {
+        let seconds = (*datetime - *now).num_seconds();
+        let distance = seconds.unsigned_abs();
+        if distance < 10 {
+            return self.now.to_string();
+        }
+
+        let (count, unit) = match distance {
+            d if d < 60 => (d, 0),
+            d if d < 3_600 => (d / 60, 1),
+            d if d < 86_400 => (d / 3_600, 2),
+            d if d < 7 * 86_400 => (d / 86_400, 3),
+            d if d < 30 * 86_400 => (d / (7 * 86_400), 4),
+            d if d < 365 * 86_400 => (d / (30 * 86_400), 5),
+            d => (d / (365 * 86_400), 6),
+        };
+        let (singular, plural) = self.units[unit];
+        let unit = match plural_category(self.code, count) {
+            "one" => singular,
+            _ => plural,
+        };
+        let amount = format!("{} {}", count, unit);
+        match seconds < 0 {
+            true => self.past.replace("{}", &amount),
+            false => self.future.replace("{}", &amount),
+        }
+    }
+
+    /// the number with the decimal and group separators of the locale, like `1.234,50`
+    pub fn number(&self, number: f64, decimals: usize) -> String {
+        let formatted = format!("{:.*}", decimals, number.abs());
+        let (integer, fraction) = match formatted.split_once('.') {
+            Some((integer, fraction)) => (integer.to_string(), Some(fraction.to_string())),
+            None => (formatted, None),
+        };
+
+        let mut grouped = String::new();
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                grouped.push_str(self.group);
+            }
+            grouped.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            grouped.push_str(self.decimal);
+            grouped.push_str(&fraction);
+        }
+
+        let is_zero = grouped.chars().all(|c| !c.is_ascii_digit() || c == '0');
+        match number.is_sign_negative() && !is_zero {
+            true => format!("-{}", grouped),
+            false => grouped,
+        }
+    }
+
+    /// the amount in a currency like `EUR` or `USD`, like `1.234,50 €`
+    pub fn currency(&self, amount: f64, currency: &str) -> String {
+        let currency = currency.trim().to_uppercase();
+        let (symbol, decimals) = match currency.as_str() {
+            "EUR" => ("€", 2),
+            "USD" => ("$", 2),
+            "GBP" => ("£", 2),
+            "JPY" => ("¥", 0),
+            "BRL" => ("R$", 2),
+            "CHF" => ("CHF", 2),
+            code => (code, 2),
+        };
+        let number = self.number(amount.abs(), decimals);
+        let formatted = self.currency.replace("{n}", &number).replace("{c}", symbol);
+        match amount < 0.0 && number.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            true => format!("-{}", formatted),
+            false => formatted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn datetime(date: (i32, u32, u32), time: (u32, u32)) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2)
+            .unwrap()
+            .and_hms_opt(time.0, time.1, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn it_formats_dates() {
+        let date = datetime((2024, 10, 17), (14, 5));
+        let de = Locale::get("de_DE.utf-8");
+        assert_eq!(de.date(&date, DateStyle::Medium), "17.10.2024");
+        assert_eq!(
+            de.date(&date, DateStyle::Full),
+            "Donnerstag, 17. Oktober 2024"
+        );
+        assert_eq!(de.time(&date), "14:05");
+
+        let en = Locale::get("en_US");
+        assert_eq!(en.date(&date, DateStyle::Long), "October 17, 2024");
+        assert_eq!(en.time(&date), "2:05 PM");
+        assert_eq!(
+            Locale::get("fr").date(&date, DateStyle::Long),
+            "17 octobre 2024"
+        );
+        assert_eq!(Locale::get("xx"), en);
+        assert_eq!(
+            Locale::get("es").format(&date, "%a %-d %b, %Y %%"),
+            Ok("jue 17 oct, 2024 %".to_string())
+        );
+        assert!(en.format(&date, "%-d %Q").is_err());
+        assert!(en.format(&date, "%H:%M %Z").is_err());
+    }
+
+    #[test]
+    fn it_formats_relative_times() {
+        let now = datetime((2024, 10, 17), (12, 0));
+        let en = Locale::get("en");
+        assert_eq!(
+            en.relative(&datetime((2024, 10, 14), (12, 0)), &now),
+            "3 days ago"
+        );
+        assert_eq!(
+            en.relative(&datetime((2024, 10, 17), (13, 0)), &now),
+            "in 1 hour"
+        );
+        assert_eq!(en.relative(&now, &now), "just now");
+        let de = Locale::get("de");
+        assert_eq!(
+            de.relative(&datetime((2022, 10, 1), (12, 0)), &now),
+            "vor 2 Jahren"
+        );
+        assert_eq!(
+            Locale::get("fr").relative(&datetime((2024, 9, 1), (12, 0)), &now),
+            "il y a 1 mois"
+        );
+    }
+
+    #[test]
+    fn it_formats_in_the_language_of_the_model() {
+        use crate::cms::content::Content;
+        use crate::cms::field::Field;
+        use crate::cms::language::{Language, Languages};
+        use crate::cms::model::{ModelBuilder, ModelKind};
+        use crate::cms::site::Site;
+
+        let mut site = Site::new(None, None, None);
+        let mut de = Language::new("de");
+        de.locale = "de_DE.utf-8".to_string();
+        site.languages = Languages::new(vec![Language::new("en"), de]);
+        let model = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .language("de")
+            .content(&Content::new(Some(
+                &[("date".to_string(), Field::new("date", Some("2024-10-17")))]
+                    .into_iter()
+                    .collect(),
+            )))
+            .build();
+
+        let date = model.content().fields["date"].to_datetime().unwrap();
+        assert_eq!(
+            model.locale(&site).date(&date, DateStyle::Long),
+            "17. Oktober 2024"
+        );
+    }
+
+    #[test]
+    fn it_formats_numbers() {
+        assert_eq!(Locale::get("en").number(1234567.891, 2), "1,234,567.89");
+        assert_eq!(Locale::get("de").number(-1234.5, 1), "-1.234,5");
+        assert_eq!(Locale::get("de").number(-0.001, 2), "0,00");
+        assert_eq!(Locale::get("en").number(999.0, 0), "999");
+        assert_eq!(Locale::get("de").currency(1234.5, "EUR"), "1.234,50\u{a0}€");
+        assert_eq!(Locale::get("en").currency(-5.0, "usd"), "-$5.00");
+        assert_eq!(Locale::get("en").currency(1500.0, "JPY"), "¥1,500");
+    }
+}
@@ -0,0 +1,190 @@
+use crate::cms::model::Model;
+use fxhash::FxHashMap;
+use std::collections::BTreeSet;
+
+/// The page tree and secondary indexes of the models of a site, by the keys of
+/// the models. Pages are indexed by their language and id, like `en/blog/whales`,
+/// since the key of the home page has no id.
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    /// keys of all models by their path, for models not stored by path
+    paths: FxHashMap<String, String>,
+    /// keys of the pages by language and id
+    ids: FxHashMap<String, String>,
+    /// keys of the child pages by the language and id of their parent, by the language
+    /// only for the pages at the top
+    children: FxHashMap<String, BTreeSet<String>>,
+    /// ids of the pages by uuid, which is the same for all translations
+    uuids: FxHashMap<String, String>,
+    /// keys of the files by the language and id of their page, `""` for the site
+    files: FxHashMap<String, BTreeSet<String>>,
+    templates: FxHashMap<String, BTreeSet<String>>,
+    languages: FxHashMap<String, BTreeSet<String>>,
+    /// keys of the users by language
+    users: FxHashMap<String, BTreeSet<String>>,
+    /// ids of the users by their lowercase email
+    emails: FxHashMap<String, String>,
+}
+
+impl Index {
+    pub fn from_models(models: &FxHashMap<String, Model>) -> Self {
+        let mut index = Self::default();
+        for (key, model) in models.iter() {
+            index.insert(key, model);
+        }
+        index
+    }
+
+    pub fn insert(&mut self, key: &str, model: &Model) {
+        self.paths.insert(model.path(), key.to_string());
+        if model.is_file() {
+            self.files
+                .entry(Self::join(model.language(), model.id().trim_matches('/')))
+                .or_default()
+                .insert(key.to_string());
+        }
+        if model.is_user() {
+            self.users
+                .entry(model.language().to_string())
+                .or_default()
+                .insert(key.to_string());
+            if !model.email().is_empty() {
+                self.emails
+                    .insert(model.email().to_lowercase(), model.id().to_string());
+            }
+        }
+        if !model.is_page() {
+            return;
+        }
+        let id = model.id().trim_matches('/');
+        self.ids
+            .insert(Self::join(model.language(), id), key.to_string());
+        let (parent, _) = id.rsplit_once('/').unwrap_or(("", id));
+        self.children
+            .entry(Self::join(model.language(), parent))
+            .or_default()
+            .insert(key.to_string());
+        if !model.uuid().is_empty() {
+            self.uuids.insert(model.uuid().to_string(), id.to_string());
+        }
+        self.templates
+            .entry(model.template().to_string())
+            .or_default()
+            .insert(key.to_string());
+        self.languages
+            .entry(model.language().to_string())
+            .or_default()
+            .insert(key.to_string());
+    }
+
+    /// remove the model from the index before it is replaced or removed from the site
+    pub fn remove(&mut self, key: &str, model: &Model) {
+        if self.paths.get(&model.path()).is_some_and(|k| k == key) {
+            self.paths.remove(&model.path());
+        }
+        if model.is_file() {
+            Self::remove_from(
+                &mut self.files,
+                &Self::join(model.language(), model.id().trim_matches('/')),
+                key,
+            );
+        }
+        if model.is_user() {
+            Self::remove_from(&mut self.users, model.language(), key);
+            let email = model.email().to_lowercase();
+            if self.emails.get(&email).is_some_and(|id| id == model.id()) {
+                self.emails.remove(&email);
+            }
+        }
+        if !model.is_page() {
+            return;
+        }
+        let id = model.id().trim_matches('/');
+        let id_key = Self::join(model.language(), id);
+        if self.ids.get(&id_key).is_some_and(|k| k == key) {
+            self.ids.remove(&id_key);
+        }
+        let (parent, _) = id.rsplit_once('/').unwrap_or(("", id));
+        Self::remove_from(
+            &mut self.children,
+            &Self::join(model.language(), parent),
+            key,
+        );
+        if self
+            .uuids
+            .get(model.uuid())
+            .is_some_and(|uuid_id| uuid_id == id)
+        {
+            self.uuids.remove(model.uuid());
+        }
+        Self::remove_from(&mut self.templates, model.template(), key);
+        Self::remove_from(&mut self.languages, model.language(), key);
+    }
+
+    fn remove_from(index: &mut FxHashMap<String, BTreeSet<String>>, name: &str, key: &str) {
+        if let Some(keys) = index.get_mut(name) {
+            keys.remove(key);
+            if keys.is_empty() {
+                index.remove(name);
+            }
+        }
+    }
+
+    fn join(lang: &str, id: &str) -> String {
+        format!("{}/{}", lang, id).trim_matches('/').to_string()
+    }
+
+    pub fn path(&self, path: &str) -> Option<&String> {
+        self.paths.get(path)
+    }
+
+    /// the key of the page with the id in the language
+    pub fn key(&self, lang: &str, id: &str) -> Option<&String> {
+        self.ids.get(&Self::join(lang, id.trim_matches('/')))
+    }
+
+    /// the key of the parent page, none for pages at the top like in kirby
+    pub fn parent(&self, model: &Model) -> Option<&String> {
+        let (parent, _) = model.id().trim_matches('/').rsplit_once('/')?;
+        self.key(model.language(), parent)
+    }
+
+    /// the keys of the child pages of the page with the id, or of the site for `""`
+    pub fn children(&self, lang: &str, id: &str) -> impl Iterator<Item = &String> {
+        self.children
+            .get(&Self::join(lang, id.trim_matches('/')))
+            .into_iter()
+            .flatten()
+    }
+
+    /// the keys of the files of the page with the id, or of the site for `""`
+    pub fn files(&self, lang: &str, id: &str) -> impl Iterator<Item = &String> {
+        self.files
+            .get(&Self::join(lang, id.trim_matches('/')))
+            .into_iter()
+            .flatten()
+    }
+
+    /// the id of the page with the uuid, like `blog/whales`
+    pub fn uuid(&self, uuid: &str) -> Option<&String> {
+        self.uuids.get(uuid)
+    }
+
+    pub fn template(&self, template: &str) -> impl Iterator<Item = &String> {
+        self.templates.get(template).into_iter().flatten()
+    }
+
+    pub fn language(&self, lang: &str) -> impl Iterator<Item = &String> {
+        self.languages.get(lang).into_iter().flatten()
+    }
+
+    /// the keys of the users in the language
+    pub fn users(&self, lang: &str) -> impl Iterator<Item = &String> {
+        self.users.get(lang).into_iter().flatten()
+    }
+
+    /// the id of the user with the email
+    pub fn email(&self, email: &str) -> Option<&String> {
+        self.emails.get(&email.trim().to_lowercase())
+    }
+}
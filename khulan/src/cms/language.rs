@@ -0,0 +1,423 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+impl From<&str> for Direction {
+    fn from(direction: &str) -> Self {
+        match direction.trim().to_lowercase().as_str() {
+            "rtl" => Direction::Rtl,
+            _ => Direction::Ltr,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Language {
+    pub code: String,
+    pub name: String,
+    pub locale: String,
+    pub direction: Direction,
+    pub default: bool,
+    pub url: Option<String>,
+    /// the language variables for the templates, like `read.more`
+    pub translations: BTreeMap<String, Variable>,
+}
+
+/// A language variable, like kirby defines them in the `translations` of a language
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Variable {
+    Text(String),
+    /// kirby's list by count, like `['No comments', 'One comment', '{{ count }} comments']`,
+    /// the last entry is used for all higher counts
+    Counted(Vec<String>),
+    /// by plural category of the locale, like `one` and `other`, or an exact count like `0`
+    Plural(BTreeMap<String, String>),
+}
+
+impl Variable {
+    /// the text for the count in the locale, `other` or the last entry if there is no better match
+    pub fn text(&self, locale: &str, count: Option<u64>) -> &str {
+        match self {
+            Variable::Text(text) => text,
+            Variable::Counted(texts) => {
+                let index = count.map_or(texts.len(), |count| count as usize);
+                texts
+                    .get(index)
+                    .or(texts.last())
+                    .map_or("", |text| text.as_str())
+            }
+            Variable::Plural(texts) => count
+                .and_then(|count| {
+                    texts
+                        .get(&count.to_string())
+                        .or_else(|| texts.get(plural_category(locale, count)))
+                })
+                .or_else(|| texts.get("other"))
+                .or_else(|| texts.values().last())
+                .map_or("", |text| text.as_str()),
+        }
+    }
+}
+
+impl Language {
+    pub fn new(code: &str) -> Self {
+        Self {
+            code: code.trim().to_lowercase(),
+            name: code.trim().to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.default
+    }
+
+    /// the locale or else the code, like `de_DE.utf-8`
+    pub fn locale(&self) -> &str {
+        match self.locale.is_empty() {
+            true => &self.code,
+            false => &self.locale,
+        }
+    }
+
+    pub fn is_rtl(&self) -> bool {
+        self.direction == Direction::Rtl
+    }
+
+    pub fn variable(&self, key: &str) -> Option<&Variable> {
+        self.translations.get(key)
+    }
+
+    /// if the language matches a tag of an `Accept-Language` header, like `de-CH`
+    pub fn matches(&self, tag: &str) -> bool {
+        let tag = tag.trim().to_lowercase().replace('_', "-");
+        let locale = self
+            .locale
+            .split('.')
+            .next()
+            .unwrap_or("")
+            .to_lowercase()
+            .replace('_', "-");
+        tag == self.code
+            || (!locale.is_empty() && tag == locale)
+            || tag.split('-').next() == Some(self.code.as_str())
+    }
+}
+
+/// Which languages have their code as prefix in the urls of the site
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LanguagePrefix {
+    /// every url starts with the language, like `/en/blog` and `/de/blog`
+    #[default]
+    All,
+    /// the default language has no prefix, like `/blog` and `/de/blog`
+    NotDefault,
+}
+
+/// The languages of the site like kirby defines them in `site/languages`.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Languages {
+    languages: Vec<Language>,
+}
+
+impl Languages {
+    pub fn new(mut languages: Vec<Language>) -> Self {
+        languages.sort_by(|a, b| b.default.cmp(&a.default).then(a.code.cmp(&b.code)));
+        // like kirby, use the first language as default if none is set
+        for (i, language) in languages.iter_mut().enumerate() {
+            language.default = i == 0;
+        }
+        Self { languages }
+    }
+
+    /// the languages of the codes with the first as default, for the setup of tests
+    #[cfg(test)]
+    pub(crate) fn from_codes(codes: &[&str]) -> Self {
+        Self::new(
+            codes
+                .iter()
+                .enumerate()
+                .map(|(i, code)| Language {
+                    default: i == 0,
+                    ..Language::new(code)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, code: &str) -> Option<&Language> {
+        self.languages.iter().find(|language| language.code == code)
+    }
+
+    pub fn contains(&self, code: &str) -> bool {
+        self.get(code).is_some()
+    }
+
+    pub fn default_language(&self) -> Option<&Language> {
+        self.languages.first()
+    }
+
+    pub fn codes(&self) -> Vec<&str> {
+        self.languages
+            .iter()
+            .map(|language| language.code())
+            .collect()
+    }
+
+    /// The language a visitor prefers from the value of an `Accept-Language` header,
+    /// like `de-CH,de;q=0.9,en;q=0.8`. Tags are tried by their quality.
+    pub fn negotiate(&self, accept_language: &str) -> Option<&Language> {
+        let mut tags: Vec<(&str, f32)> = accept_language
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map(|q| q.trim().parse::<f32>().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+                (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // stable, so tags with the same quality keep the order of the header
+        tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        tags.iter().find_map(|(tag, _)| match *tag {
+            "*" => self.default_language(),
+            tag => self.languages.iter().find(|language| language.matches(tag)),
+        })
+    }
+
+    /// The variable in the language, or in the default language if it is not translated.
+    pub fn variable(&self, lang: &str, key: &str) -> Option<(&Language, &Variable)> {
+        self.get(lang)
+            .into_iter()
+            .chain(self.default_language())
+            .find_map(|language| language.variable(key).map(|variable| (language, variable)))
+    }
+
+    /// The translated text of a language variable with its `{{ placeholders }}`
+    /// replaced by the data, like kirby's `t()`.
+    pub fn translate(&self, lang: &str, key: &str, data: &[(&str, &str)]) -> Option<String> {
+        let (language, variable) = self.variable(lang, key)?;
+        Some(template(variable.text(language.locale(), None), data))
+    }
+
+    /// The translated text of a language variable for a count, with the
+    /// plural rules of the locale of the language, like kirby's `tc()`.
+    /// The count is available as `{{ count }}` placeholder.
+    pub fn translate_count(
+        &self,
+        lang: &str,
+        key: &str,
+        count: u64,
+        data: &[(&str, &str)],
+    ) -> Option<String> {
+        let (language, variable) = self.variable(lang, key)?;
+        let count_text = count.to_string();
+        let mut data = data.to_vec();
+        data.push(("count", &count_text));
+        Some(template(
+            variable.text(language.locale(), Some(count)),
+            &data,
+        ))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Language> {
+        self.languages.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.languages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_empty()
+    }
+}
+
+/// replace `{{ name }}` and `{name}` placeholders with the data
+pub fn template(text: &str, data: &[(&str, &str)]) -> String {
+    let mut text = text.to_string();
+    for (name, value) in data {
+        for placeholder in [
+            format!("{{{{ {} }}}}", name),
+            format!("{{{{{}}}}}", name),
+            format!("{{{}}}", name),
+        ] {
+            text = text.replace(&placeholder, value);
+        }
+    }
+    text
+}
+
+/// The CLDR plural category of a count in a locale, like `one` or `few`.
+/// Covers the rules of the common european and asian languages, other
+/// languages use `one` for 1 and `other` for the rest.
+pub fn plural_category(locale: &str, count: u64) -> &'static str {
+    let language = locale
+        .split(['_', '-', '.'])
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+    let (n10, n100) = (count % 10, count % 100);
+    let few = (2..=4).contains(&n10) && !(12..=14).contains(&n100);
+
+    match language.as_str() {
+        "ja" | "ko" | "zh" | "th" | "vi" | "id" | "ms" => "other",
+        "fr" if count < 2 => "one",
+        "pt" if count < 2 && locale.to_lowercase().contains("br") => "one",
+        "ru" | "uk" | "be" | "sr" | "hr" | "bs" => match count {
+            _ if n10 == 1 && n100 != 11 => "one",
+            _ if few => "few",
+            _ => "many",
+        },
+        "pl" => match count {
+            1 => "one",
+            _ if few => "few",
+            _ => "many",
+        },
+        "cs" | "sk" => match count {
+            1 => "one",
+            2..=4 => "few",
+            _ => "other",
+        },
+        "ar" => match count {
+            0 => "zero",
+            1 => "one",
+            2 => "two",
+            _ if (3..=10).contains(&n100) => "few",
+            _ if (11..=99).contains(&n100) => "many",
+            _ => "other",
+        },
+        _ if count == 1 => "one",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_has_a_default_language() {
+        let mut de = Language::new("de");
+        de.direction = Direction::from("ltr");
+        let mut en = Language::new("EN");
+        en.default = true;
+        let languages = Languages::new(vec![de, en]);
+
+        assert_eq!(languages.len(), 2);
+        assert_eq!(languages.codes(), vec!["en", "de"]);
+        assert_eq!(languages.default_language().unwrap().code(), "en");
+        assert!(languages.contains("de"));
+        assert!(!languages.contains("fr"));
+
+        let languages = Languages::new(vec![Language::new("fr"), Language::new("ar")]);
+        assert_eq!(languages.default_language().unwrap().code(), "ar");
+    }
+
+    #[test]
+    fn it_translates_variables() {
+        let mut en = Language::new("en");
+        en.default = true;
+        en.translations = BTreeMap::from([
+            (
+                "read.more".to_string(),
+                Variable::Text("Read more".to_string()),
+            ),
+            (
+                "hello".to_string(),
+                Variable::Text("Hello {{ name }}".to_string()),
+            ),
+        ]);
+        let mut de = Language::new("de");
+        de.translations = BTreeMap::from([
+            (
+                "hello".to_string(),
+                Variable::Text("Hallo {name}".to_string()),
+            ),
+            (
+                "comments".to_string(),
+                Variable::Counted(vec![
+                    "Keine Kommentare".to_string(),
+                    "Ein Kommentar".to_string(),
+                    "{{ count }} Kommentare".to_string(),
+                ]),
+            ),
+        ]);
+        let mut ru = Language::new("ru");
+        ru.locale = "ru_RU".to_string();
+        ru.translations = BTreeMap::from([(
+            "comments".to_string(),
+            Variable::Plural(BTreeMap::from([
+                ("one".to_string(), "{{ count }} комментарий".to_string()),
+                ("few".to_string(), "{{ count }} комментария".to_string()),
+                ("many".to_string(), "{{ count }} комментариев".to_string()),
+            ])),
+        )]);
+        let languages = Languages::new(vec![en, de, ru]);
+
+        assert_eq!(
+            languages.translate("de", "hello", &[("name", "Khulan")]),
+            Some("Hallo Khulan".to_string())
+        );
+        // falls back to the default language
+        assert_eq!(
+            languages.translate("de", "read.more", &[]),
+            Some("Read more".to_string())
+        );
+        assert_eq!(languages.translate("de", "unknown", &[]), None);
+
+        let de = |count| languages.translate_count("de", "comments", count, &[]);
+        assert_eq!(de(0), Some("Keine Kommentare".to_string()));
+        assert_eq!(de(1), Some("Ein Kommentar".to_string()));
+        assert_eq!(de(12), Some("12 Kommentare".to_string()));
+
+        let ru = |count| languages.translate_count("ru", "comments", count, &[]);
+        assert_eq!(ru(21), Some("21 комментарий".to_string()));
+        assert_eq!(ru(3), Some("3 комментария".to_string()));
+        assert_eq!(ru(11), Some("11 комментариев".to_string()));
+    }
+
+    #[test]
+    fn it_has_plural_rules() {
+        assert_eq!(plural_category("en", 1), "one");
+        assert_eq!(plural_category("en_US.utf-8", 0), "other");
+        assert_eq!(plural_category("fr_FR", 0), "one");
+        assert_eq!(plural_category("pl", 22), "few");
+        assert_eq!(plural_category("pl", 25), "many");
+        assert_eq!(plural_category("ar", 2), "two");
+        assert_eq!(plural_category("ja", 1), "other");
+    }
+
+    #[test]
+    fn it_negotiates_the_accept_language_header() {
+        let mut en = Language::new("en");
+        en.default = true;
+        let mut pt = Language::new("pt");
+        pt.locale = "pt_BR.utf-8".to_string();
+        let languages = Languages::new(vec![en, Language::new("de"), pt]);
+
+        let code = |header: &str| languages.negotiate(header).map(|l| l.code());
+        assert_eq!(code("de-CH,de;q=0.9,en;q=0.8"), Some("de"));
+        assert_eq!(code("fr-FR, en;q=0.5, de;q=0.7"), Some("de"));
+        assert_eq!(code("pt-BR"), Some("pt"));
+        assert_eq!(code("fr, *;q=0.1"), Some("en"));
+        assert_eq!(code("de;q=0, fr"), None);
+        assert_eq!(code(""), None);
+    }
+}
@@ -0,0 +1,351 @@
+use crate::cms::model::Model;
+use crate::cms::site::Site;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageReader};
+use serde::Serialize;
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// The metadata of an image, read once when the file is loaded and cached until it
+/// is modified
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ImageMeta {
+    /// the width as displayed, after the rotation of its exif orientation
+    pub width: u32,
+    pub height: u32,
+    /// the width divided by the height like kirby's `ratio()`
+    pub ratio: f64,
+    pub orientation: Orientation,
+    pub exif: Option<Exif>,
+    /// a placeholder for lazy loading, see https://blurha.sh
+    pub blurhash: Option<String>,
+    /// a tiny jpeg as data uri for lazy loading
+    pub placeholder: Option<String>,
+    #[serde(skip)]
+    pub modified: SystemTime,
+}
+
+// the ratio and the location are read from images and never NaN
+impl Eq for ImageMeta {}
+
+/// The orientation of an image like kirby's `orientation()`
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+    Square,
+}
+
+/// The exif data of a photo like kirby's `exif()`
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Exif {
+    /// the time the photo was taken like `2024-10-17 09:30:00`
+    pub date: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// the exposure time like `1/250`
+    pub exposure: Option<String>,
+    /// the aperture like `f/2.8`
+    pub aperture: Option<String>,
+    pub iso: Option<u32>,
+    /// the focal length like `35mm`
+    pub focal_length: Option<String>,
+    /// the rotation of the pixels from 1 to 8, 1 if they are not rotated
+    pub orientation: u32,
+    pub location: Option<Location>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct Location {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl ImageMeta {
+    /// Read the metadata of the image. None for files that are not images which can be
+    /// decoded, like svgs.
+    pub fn read(path: &Path) -> Option<Self> {
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()?;
+        let image = ImageReader::open(path)
+            .ok()?
+            .with_guessed_format()
+            .ok()?
+            .decode()
+            .ok()?;
+        let exif = Exif::read(path);
+        let (width, height) = match exif.as_ref().map_or(1, |exif| exif.orientation) {
+            // rotated by 90 or 270 degrees
+            5..=8 => (image.height(), image.width()),
+            _ => (image.width(), image.height()),
+        };
+        Some(Self {
+            width,
+            height,
+            ratio: width as f64 / height.max(1) as f64,
+            orientation: match width.cmp(&height) {
+                std::cmp::Ordering::Greater => Orientation::Landscape,
+                std::cmp::Ordering::Less => Orientation::Portrait,
+                std::cmp::Ordering::Equal => Orientation::Square,
+            },
+            blurhash: blurhash(&image),
+            placeholder: placeholder(&image),
+            exif,
+            modified,
+        })
+    }
+}
+
+fn blurhash(image: &DynamicImage) -> Option<String> {
+    let small = image.thumbnail(32, 32).to_rgba8();
+    let (x, y) = match small.width() >= small.height() {
+        true => (4, 3),
+        false => (3, 4),
+    };
+    blurhash::encode(x, y, small.width(), small.height(), small.as_raw()).ok()
+}
+
+fn placeholder(image: &DynamicImage) -> Option<String> {
+    let small = DynamicImage::ImageRgb8(image.thumbnail(16, 16).to_rgb8());
+    let mut bytes = vec![];
+    small
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, 60))
+        .ok()?;
+    Some(format!(
+        "data:image/jpeg;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+impl Exif {
+    /// the exif data of jpegs, tiffs, pngs, webps and heifs with exif data
+    pub fn read(path: &Path) -> Option<Self> {
+        let file = fs::File::open(path).ok()?;
+        let data = exif::Reader::new()
+            .read_from_container(&mut BufReader::new(file))
+            .ok()?;
+        let field = |tag: exif::Tag| data.get_field(tag, exif::In::PRIMARY);
+        let text = |tag: exif::Tag| {
+            field(tag).and_then(|field| match &field.value {
+                exif::Value::Ascii(values) => values
+                    .first()
+                    .map(|value| String::from_utf8_lossy(value).trim().to_string())
+                    .filter(|value| !value.is_empty()),
+                _ => None,
+            })
+        };
+        let rational = |tag: exif::Tag| {
+            field(tag).and_then(|field| match &field.value {
+                exif::Value::Rational(values) => values.first().map(|value| value.to_f64()),
+                _ => None,
+            })
+        };
+        let uint = |tag: exif::Tag| field(tag).and_then(|field| field.value.get_uint(0));
+
+        Some(Self {
+            // `2024:10:17 09:30:00` in exif
+            date: text(exif::Tag::DateTimeOriginal)
+                .or_else(|| text(exif::Tag::DateTime))
+                .map(|date| date.replacen(':', "-", 2)),
+            make: text(exif::Tag::Make),
+            model: text(exif::Tag::Model),
+            exposure: field(exif::Tag::ExposureTime).and_then(|field| match &field.value {
+                exif::Value::Rational(values) => {
+                    values
+                        .first()
+                        .map(|value| match value.num < value.denom && value.num > 0 {
+                            true => {
+                                format!("1/{}", (value.denom as f64 / value.num as f64).round())
+                            }
+                            false => format!("{}", value.to_f64()),
+                        })
+                }
+                _ => None,
+            }),
+            aperture: rational(exif::Tag::FNumber).map(|f| format!("f/{}", round(f))),
+            iso: uint(exif::Tag::PhotographicSensitivity),
+            focal_length: rational(exif::Tag::FocalLength).map(|mm| format!("{}mm", round(mm))),
+            orientation: uint(exif::Tag::Orientation)
+                .filter(|orientation| (1..=8).contains(orientation))
+                .unwrap_or(1),
+            location: Location::read(&data),
+        })
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+impl Location {
+    fn read(data: &exif::Exif) -> Option<Self> {
+        let degrees = |tag: exif::Tag, reference: exif::Tag, negative: &str| {
+            let value = match &data.get_field(tag, exif::In::PRIMARY)?.value {
+                exif::Value::Rational(values) if values.len() == 3 => {
+                    values[0].to_f64() + values[1].to_f64() / 60.0 + values[2].to_f64() / 3600.0
+                }
+                _ => return None,
+            };
+            let negative = data
+                .get_field(reference, exif::In::PRIMARY)
+                .is_some_and(|field| match &field.value {
+                    exif::Value::Ascii(values) => values
+                        .first()
+                        .is_some_and(|value| value.as_slice() == negative.as_bytes()),
+                    _ => false,
+                });
+            Some(if negative { -value } else { value })
+        };
+        Some(Self {
+            lat: degrees(exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, "S")?,
+            lng: degrees(exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, "W")?,
+        })
+    }
+}
+
+impl Model {
+    /// the width and height of images as displayed, like kirby's `dimensions()`
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        self.image_meta().map(|meta| (meta.width, meta.height))
+    }
+
+    pub fn exif(&self) -> Option<&Exif> {
+        self.image_meta().and_then(|meta| meta.exif.as_ref())
+    }
+}
+
+impl Site {
+    /// The metadata of the image file, from the cache if the file is not modified since
+    /// it was read. The location is removed if the site strips gps data, but the file is
+    /// not changed.
+    pub fn image_meta(&mut self, file: &str) -> Option<ImageMeta> {
+        let modified = fs::metadata(file)
+            .and_then(|metadata| metadata.modified())
+            .ok()?;
+        let meta = match self.images.get(file) {
+            Some(meta) if meta.modified == modified => meta.clone(),
+            _ => {
+                let meta = ImageMeta::read(Path::new(file))?;
+                self.images.insert(file.to_string(), meta.clone());
+                meta
+            }
+        };
+        Some(match (self.strips_gps(), meta) {
+            (true, mut meta) => {
+                if let Some(exif) = meta.exif.as_mut() {
+                    exif.location = None;
+                }
+                meta
+            }
+            (false, meta) => meta,
+        })
+    }
+
+    /// remove the metadata of images which are not files of the site anymore
+    pub fn prune_image_meta(&mut self) {
+        let files: std::collections::HashSet<String> = self
+            .models
+            .values()
+            .filter_map(|model| model.file_root())
+            .collect();
+        self.images.retain(|file, _| files.contains(file));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::site::SiteBuilder;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn it_reads_image_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("whale.png");
+        RgbImage::from_fn(40, 20, |x, _| Rgb([(x * 6) as u8, 100, 200]))
+            .save(&path)
+            .unwrap();
+
+        let meta = ImageMeta::read(&path).unwrap();
+        assert_eq!((meta.width, meta.height), (40, 20));
+        assert_eq!(meta.ratio, 2.0);
+        assert_eq!(meta.orientation, Orientation::Landscape);
+        assert_eq!(meta.exif, None);
+        assert_eq!(meta.blurhash.as_ref().unwrap().len(), 4 + 2 * 4 * 3);
+        assert!(meta
+            .placeholder
+            .unwrap()
+            .starts_with("data:image/jpeg;base64,"));
+
+        let mut site = SiteBuilder::new().build();
+        let file = path.to_string_lossy();
+        assert!(site.image_meta(&file).is_some());
+        assert!(site.images.contains_key(file.as_ref()));
+        site.prune_image_meta();
+        assert!(site.images.is_empty());
+
+        std::fs::write(temp_dir.path().join("notes.txt"), "not an image").unwrap();
+        assert_eq!(ImageMeta::read(&temp_dir.path().join("notes.txt")), None);
+    }
+
+    #[test]
+    fn it_reads_exif_data() {
+        use exif::experimental::Writer;
+        use exif::{Field, In, Rational, Tag, Value};
+
+        let field = |tag: Tag, value: Value| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        };
+        let rational = |num: u32, denom: u32| Rational { num, denom };
+        let fields = [
+            field(Tag::Make, Value::Ascii(vec![b"Khulan".to_vec()])),
+            field(
+                Tag::DateTimeOriginal,
+                Value::Ascii(vec![b"2024:10:17 09:30:00".to_vec()]),
+            ),
+            field(Tag::ExposureTime, Value::Rational(vec![rational(1, 250)])),
+            field(Tag::FNumber, Value::Rational(vec![rational(28, 10)])),
+            field(Tag::Orientation, Value::Short(vec![6])),
+            field(
+                Tag::GPSLatitude,
+                Value::Rational(vec![rational(47, 1), rational(30, 1), rational(0, 1)]),
+            ),
+            field(Tag::GPSLatitudeRef, Value::Ascii(vec![b"N".to_vec()])),
+            field(
+                Tag::GPSLongitude,
+                Value::Rational(vec![rational(106, 1), rational(54, 1), rational(0, 1)]),
+            ),
+            field(Tag::GPSLongitudeRef, Value::Ascii(vec![b"W".to_vec()])),
+        ];
+        let mut writer = Writer::new();
+        for field in fields.iter() {
+            writer.push_field(field);
+        }
+        let mut tiff = std::io::Cursor::new(vec![]);
+        writer.write(&mut tiff, false).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("photo.tif");
+        std::fs::write(&path, tiff.into_inner()).unwrap();
+
+        let exif = Exif::read(&path).unwrap();
+        assert_eq!(exif.make.as_deref(), Some("Khulan"));
+        assert_eq!(exif.date.as_deref(), Some("2024-10-17 09:30:00"));
+        assert_eq!(exif.exposure.as_deref(), Some("1/250"));
+        assert_eq!(exif.aperture.as_deref(), Some("f/2.8"));
+        assert_eq!(exif.orientation, 6);
+        assert_eq!(
+            exif.location,
+            Some(Location {
+                lat: 47.5,
+                lng: -106.9
+            })
+        );
+    }
+}
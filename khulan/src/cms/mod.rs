@@ -1,5 +1,19 @@
+pub mod blueprint;
 pub mod content;
+pub mod deserializer;
 pub mod field;
+pub mod file;
+pub mod format;
+pub mod index;
+pub mod language;
+pub mod metadata;
 pub mod model;
-// pub mod page;
+pub mod navigation;
+pub mod page;
+pub mod pages;
 pub mod site;
+pub mod sitemap;
+pub mod thumb;
+pub mod translation;
+pub mod user;
+pub mod validation;
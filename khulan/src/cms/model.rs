@@ -1,8 +1,13 @@
+use crate::cms::blueprint::{Blueprint, FieldKind, NumScheme};
 use crate::cms::content::Content;
 use crate::cms::field::Field;
+use crate::cms::format::Locale;
+use crate::cms::metadata::ImageMeta;
 use crate::cms::model::ModelKind::File;
+use crate::cms::pages::Pages;
 use crate::cms::site::Site;
 use rocket::serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
 use std::time::SystemTime;
 
@@ -19,6 +24,22 @@ pub struct Model {
     #[serde(skip)]
     root: String,
     last_modified: SystemTime,
+    /// fields inherited from the default language
+    #[serde(skip)]
+    fallback: BTreeSet<String>,
+    /// path of slugs in the language of the model, set by the site
+    uri: String,
+    /// absolute urls of the model by language, set by the site
+    urls: BTreeMap<String, String>,
+    /// the path of the url with the language prefix if it has one, set by the site
+    #[serde(skip)]
+    route: String,
+    /// if the model is the home page of the site, which has no path in urls
+    #[serde(skip)]
+    home: bool,
+    /// the dimensions, exif data and placeholders of images, set by the site
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<ImageMeta>,
 }
 
 impl Model {
@@ -36,31 +57,87 @@ impl Model {
         }
     }
 
+    /// the folder path of the model without numbers and language, like `blog/whales`
+    pub fn id(&self) -> &str {
+        &self.path
+    }
+
+    /// the translated `slug` field or the folder name
+    pub fn slug(&self) -> &str {
+        if self.is_translated("slug") {
+            let slug = self.content.fields.get("slug").unwrap().value().trim();
+            if !slug.is_empty() {
+                return slug;
+            }
+        }
+        self.path.rsplit('/').next().unwrap_or("")
+    }
+
+    /// the path of translated slugs of the model and its parents, like `blog/wale`
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// the path of the url with the language prefix, like `de/blog/wale`
+    pub fn url_path(&self) -> String {
+        if !self.route.is_empty() || self.urls.contains_key(&self.language) {
+            return self.route.clone();
+        }
+        format!("{}/{}", self.language, self.uri)
+            .trim_matches('/')
+            .to_string()
+    }
+
+    pub(crate) fn set_urls(&mut self, uri: &str, route: &str, urls: BTreeMap<String, String>) {
+        self.uri = uri.to_string();
+        self.route = route.to_string();
+        self.urls = urls;
+    }
+
     // NOTE: since path is used in hashmaps a key, it's better for convenience to return string here than a reference to a str
     pub fn path(&self) -> String {
+        self.path_in(&self.language)
+    }
+
+    /// the logical page shared by all translations of the model, like `blog/whales`
+    pub fn identity(&self) -> String {
+        self.path_in("")
+    }
+
+    /// the path of the same model in another language
+    pub(crate) fn path_in(&self, language: &str) -> String {
         let mut path = self.path.clone();
-        if path == "home" {
-            // TODO: make this configurable
+        if self.home {
             path = "".to_string();
         }
         if self.kind == ModelKind::Site {
             path = "$".to_string(); // hack to make site model not overlap with home
         }
+        if self.kind == ModelKind::User {
+            // users do not overlap with pages of the same id
+            path = format!("@/{}", self.path);
+        }
         if self.kind == File {
-            path = format!(
-                "{}/{}",
-                self.path,
-                PathBuf::from(&self.root)
-                    .file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-            );
+            let mut filename = PathBuf::from(&self.root)
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            let suffix = format!(".{}.txt", self.language);
+            if !self.language.is_empty() && filename.ends_with(&suffix) {
+                let name = filename.trim_end_matches(&suffix);
+                filename = match language.is_empty() {
+                    true => format!("{}.txt", name),
+                    false => format!("{}.{}.txt", name, language),
+                };
+            }
+            path = format!("{}/{}", self.path, filename);
         }
 
         // the following works for both single and multi-language sites
         // because of the trim and lang being "" for single language
-        format!("{}/{}", self.language, path)
+        format!("{}/{}", language, path)
             .trim_matches('/')
             .to_string()
     }
@@ -69,25 +146,42 @@ impl Model {
         self.last_modified
     }
 
-    pub fn num(&self) -> Option<u16> {
+    /// the sorting number of listed pages, which can be zero or a date like `20241017`
+    pub fn num(&self) -> Option<u64> {
         if self.num.is_empty() {
             if self.kind == File {
                 // try sort field, like for files
-                match self.content.fields.get("sort") {
-                    Some(sort) => match sort.value().parse::<u16>() {
-                        Ok(num) => Some(num),
-                        Err(_) => None,
-                    },
-                    None => None,
-                }
+                self.content
+                    .fields
+                    .get("sort")
+                    .and_then(|sort| sort.value().trim().parse::<u64>().ok())
             } else {
                 None
             }
         } else {
-            match self.num.parse::<u16>() {
-                Ok(num) => Some(num),
-                Err(_) => None,
-            }
+            self.num.parse::<u64>().ok()
+        }
+    }
+
+    /// The num the page should have by the `num` option of its blueprint, like
+    /// `20241017` for `num: date`. None for pages that are not listed.
+    pub fn expected_num(&self, site: &Site) -> Option<u64> {
+        if !self.is_listed() {
+            return None;
+        }
+        let scheme = self
+            .blueprint(site)
+            .map(|blueprint| blueprint.num.clone())
+            .unwrap_or_default();
+        match scheme {
+            NumScheme::Default => self.num(),
+            NumScheme::Zero => Some(0),
+            NumScheme::Date(field) => self
+                .content
+                .fields
+                .get(&field)
+                .and_then(|date| date.to_datetime())
+                .and_then(|date| date.format("%Y%m%d").to_string().parse().ok()),
         }
     }
 
@@ -110,6 +204,96 @@ impl Model {
         }
     }
 
+    /// the locale of the language of the model to format dates and numbers
+    pub fn locale(&self, site: &Site) -> &'static Locale {
+        let language = site
+            .language(&self.language)
+            .or_else(|| site.default_language());
+        Locale::get(language.map_or(&self.language, |language| language.locale()))
+    }
+
+    pub fn content(&self) -> &Content {
+        &self.content
+    }
+
+    /// if the field is stored in the content file of the model itself
+    /// and not inherited from the default language
+    pub fn is_translated(&self, field: &str) -> bool {
+        let field = field.to_lowercase();
+        self.content.fields.contains_key(&field) && !self.fallback.contains(&field)
+    }
+
+    /// Fill the fields missing in this translation from the content of the default language.
+    /// Fields in `untranslatable` always use the default value, like `translate: false` in kirby.
+    pub fn merge_translation(&mut self, source: Option<&Content>, untranslatable: &[String]) {
+        for name in std::mem::take(&mut self.fallback) {
+            self.content.fields.remove(&name);
+        }
+
+        if let Some(source) = source {
+            for (name, field) in source.fields.iter() {
+                // the slug of a translation is never inherited
+                if name == "slug" {
+                    continue;
+                }
+                if !self.content.fields.contains_key(name) || untranslatable.contains(name) {
+                    self.content.fields.insert(name.clone(), field.clone());
+                    self.fallback.insert(name.clone());
+                }
+            }
+        }
+    }
+
+    /// name of the blueprint for the template of the model, like `pages/post` or `files/image`
+    pub fn blueprint_name(&self) -> String {
+        match self.kind {
+            ModelKind::Site => "site".to_string(),
+            ModelKind::File => format!("files/{}", self.template()),
+            ModelKind::User => format!("users/{}", self.template()),
+            _ => format!("pages/{}", self.template()),
+        }
+    }
+
+    pub fn blueprint<'a>(&self, site: &'a Site) -> Option<&'a Blueprint> {
+        site.blueprint(&self.blueprint_name())
+            .or_else(|| match self.kind {
+                ModelKind::File => site.blueprint("files/default"),
+                ModelKind::User => site.blueprint("users/default"),
+                ModelKind::Page => site.blueprint("pages/default"),
+                _ => None,
+            })
+    }
+
+    /// set the declared kind of each field of the content from the blueprint, and no kind for
+    /// the fields it does not declare
+    pub fn apply_blueprint(&mut self, blueprint: &Blueprint) {
+        for (name, field) in self.content.fields.iter_mut() {
+            match blueprint.field(name) {
+                Some(declared) => field.set_kind(&declared.kind),
+                None => field.set_kind(&FieldKind::None),
+            }
+        }
+    }
+
+    pub fn is_home(&self) -> bool {
+        self.home
+    }
+
+    /// set by the site for the page with its home id, since the key of the model changes
+    pub(crate) fn set_home(&mut self, home: bool) {
+        self.home = home && self.kind == ModelKind::Page;
+    }
+
+    /// the metadata of images, set when the file is loaded
+    pub fn image_meta(&self) -> Option<&ImageMeta> {
+        self.image.as_ref()
+    }
+
+    /// set by the site for files when they are loaded
+    pub(crate) fn set_image_meta(&mut self, image: Option<ImageMeta>) {
+        self.image = image.filter(|_| self.kind == File);
+    }
+
     pub fn is_page(&self) -> bool {
         self.kind == ModelKind::Page
     }
@@ -126,12 +310,23 @@ impl Model {
         self.kind == ModelKind::User
     }
 
+    #[deprecated(note = "use `file_root`, the file by its name is `file_named`")]
     pub fn file(&self) -> Option<String> {
-        if self.kind == File {
-            Some(self.root.trim_end_matches(".txt").to_string())
-        } else {
-            None
+        self.file_root()
+    }
+
+    /// the path of the media file of file models, without the `.txt` of the metadata
+    pub fn file_root(&self) -> Option<String> {
+        if self.kind != File {
+            return None;
         }
+        let meta = format!(".{}.txt", self.language);
+        let root = self
+            .root
+            .strip_suffix(&meta)
+            .or_else(|| self.root.strip_suffix(".txt"))
+            .unwrap_or(&self.root);
+        Some(root.to_string())
     }
 
     pub fn root(&self) -> String {
@@ -142,65 +337,102 @@ impl Model {
         self.last_modified
     }
 
-    // infer these from the path and num
+    /// if the folder of the model is in a `_drafts` folder
     pub fn is_draft(&self) -> bool {
-        let root_path = PathBuf::from(&self.root);
-
-        if let Some(parent) = root_path.parent() {
-            parent.to_str().map_or(false, |p| p.contains("_drafts"))
-        } else {
-            false
-        }
+        PathBuf::from(&self.root)
+            .parent()
+            .and_then(|folder| folder.parent())
+            .and_then(|parent| parent.file_name())
+            .is_some_and(|name| name == DRAFTS)
     }
 
-    fn is_unlisted(&self) -> bool {
-        self.num.is_empty()
+    pub fn is_unlisted(&self) -> bool {
+        !self.is_draft() && self.num.is_empty()
     }
-    fn is_listed(&self) -> bool {
-        !self.is_unlisted()
+
+    pub fn is_listed(&self) -> bool {
+        !self.is_draft() && !self.num.is_empty()
     }
-    fn is_published(&self) -> bool {
-        !self.is_draft()
+
+    /// if neither the model nor one of its parents is a draft
+    pub fn is_published(&self) -> bool {
+        !PathBuf::from(&self.root)
+            .parent()
+            .is_some_and(|folder| folder.iter().any(|segment| segment == DRAFTS))
     }
 
-    pub fn parent(&self, site: &Site) -> Option<Model> {
-        // Split the path by '/' and remove the last segment
-        let mut segments: Vec<&str> = self.path.split('/').collect();
-        if segments.pop().is_none() {
-            return None; // If there's no parent
+    pub fn status(&self) -> Status {
+        match (self.is_draft(), self.is_listed()) {
+            (true, _) => Status::Draft,
+            (false, true) => Status::Listed,
+            (false, false) => Status::Unlisted,
         }
+    }
 
-        // Join the remaining segments back into a parent path
-        let parent_path = segments.join("/");
+    /// the parent page in the language of the model, none for pages at the top
+    pub fn parent<'a>(&self, site: &'a Site) -> Option<&'a Model> {
+        site.index.parent(self).and_then(|key| site.models.get(key))
+    }
 
-        // Search the models in the site to find the one with a matching path
-        let model = site.models.values().find(|model| model.path == parent_path);
+    /// the child pages in the language of the model, by num and then by id like the folders
+    pub fn children<'a>(&self, site: &'a Site) -> Pages<'a> {
+        site.index
+            .children(&self.language, self.id())
+            .filter_map(|key| site.models.get(key))
+            .collect::<Pages>()
+            .sort_by_folder()
+    }
 
-        match model {
-            None => None,
-            Some(m) => Some(m.clone()),
+    pub fn url(&self) -> String {
+        match self.url_for(&self.language) {
+            Some(url) => url.to_string(),
+            None => format!("/{}", self.path()),
         }
     }
 
-    pub fn children(&self, site: &Site) -> Vec<Model> {
-        // let mut children = vec![];
-        site.models
-            .values()
-            .filter(|model| {
-                model.path.starts_with(&self.path) && model.path != self.path
-                // && model.path.split('/').count() == self.path.split('/').count() + 1
-            })
-            .map(|m| m.clone())
+    /// the variant of the model in the language, if there is a content file for it
+    pub fn translation<'a>(&self, site: &'a Site, lang: &str) -> Option<&'a Model> {
+        site.models.get(&self.path_in(lang))
+    }
+
+    /// all language variants of the model including itself, in the order of the site languages
+    pub fn translations<'a>(&self, site: &'a Site) -> Vec<&'a Model> {
+        if site.languages.is_empty() {
+            return site.models.get(&self.path()).into_iter().collect();
+        }
+        site.languages
+            .iter()
+            .filter_map(|language| self.translation(site, language.code()))
             .collect()
     }
 
-    pub fn url(&self) -> String {
-        format!("http:://localhost:8000/{}", self.path)
-        // TODO: add url from site()
-        // format!("{}/{}", self.site.url(), self.path.to_string_lossy())
+    /// the scheme and host of the url, like `https://example.de`, empty if it has none
+    pub fn origin(&self) -> String {
+        match url::Url::parse(&self.url()) {
+            Ok(url) if url.has_host() => url.origin().ascii_serialization(),
+            _ => String::new(),
+        }
+    }
+
+    /// the url of the translation of the model in the language
+    pub fn url_for(&self, lang: &str) -> Option<&str> {
+        self.urls.get(lang).map(|url| url.as_str())
     }
 }
 
+/// the folder of drafts, which are not part of the id of the pages in it
+pub const DRAFTS: &str = "_drafts";
+
+/// The status of a page like in kirby: drafts are in `_drafts` folders, listed
+/// pages have a num like `1_whales` and unlisted pages have none.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum Status {
+    Draft,
+    Unlisted,
+    Listed,
+}
+
 #[derive(Serialize, Debug, Clone, PartialEq, Eq)]
 pub enum ModelKind {
     Page,
@@ -303,6 +535,13 @@ impl ModelBuilder {
             content: self.content.clone(),
             last_modified: self.last_modified,
             root: self.root.clone(),
+            fallback: BTreeSet::new(),
+            uri: "".to_string(),
+            urls: BTreeMap::new(),
+            route: String::new(),
+            // the default of kirby, sites with another home page id set it when loading
+            home: self.kind == ModelKind::Page && self.path.trim_matches('/') == "home",
+            image: None,
         }
     }
 }
@@ -339,4 +578,68 @@ mod tests {
         assert_eq!(model.is_unlisted(), false);
         assert_eq!(model.is_listed(), true);
     }
+
+    #[test]
+    fn it_has_a_status() {
+        let page = |root: &str, num: &str| {
+            ModelBuilder::new()
+                .kind(&ModelKind::Page)
+                .path("blog/post")
+                .num(num)
+                .root(root)
+                .build()
+        };
+
+        let draft = page("/content/blog/_drafts/post/post.txt", "");
+        assert_eq!(draft.status(), Status::Draft);
+        assert!(!draft.is_published());
+        assert!(!draft.is_listed() && !draft.is_unlisted());
+
+        // the child of a draft is listed but not published
+        let child = page("/content/blog/_drafts/post/1_child/post.txt", "1");
+        assert_eq!(child.status(), Status::Listed);
+        assert!(!child.is_published());
+
+        let zero = page("/content/blog/0_post/post.txt", "0");
+        assert_eq!(zero.status(), Status::Listed);
+        assert_eq!(zero.num(), Some(0));
+
+        let dated = page("/content/blog/20241017_post/post.txt", "20241017");
+        assert_eq!(dated.num(), Some(20241017));
+        assert!(dated.is_published());
+
+        let unlisted = page("/content/my_drafts/post.txt", "");
+        assert_eq!(unlisted.status(), Status::Unlisted);
+    }
+
+    #[test]
+    fn it_gets_the_num_of_the_blueprint() {
+        let mut site = Site::new(None, None, None);
+        site.blueprints.insert(
+            "pages/post".to_string(),
+            Blueprint {
+                name: "pages/post".to_string(),
+                num: NumScheme::from("{{ page.published.toDate('Ymd') }}"),
+                ..Default::default()
+            },
+        );
+        let page = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("blog/post")
+            .template("post")
+            .num("3")
+            .content(&Content::new(Some(
+                &[(
+                    "published".to_string(),
+                    Field::new("published", Some("2024-10-17 12:00")),
+                )]
+                .into_iter()
+                .collect(),
+            )))
+            .build();
+        assert_eq!(page.expected_num(&site), Some(20241017));
+
+        site.blueprints.get_mut("pages/post").unwrap().num = NumScheme::Zero;
+        assert_eq!(page.expected_num(&site), Some(0));
+    }
 }
@@ -0,0 +1,257 @@
+use crate::cms::model::Model;
+use crate::cms::pages::Pages;
+use crate::cms::site::Site;
+use serde::Serialize;
+
+impl Model {
+    /// the pages with the same parent including the page itself, like in kirby
+    pub fn siblings<'a>(&self, site: &'a Site) -> Pages<'a> {
+        match self.parent(site) {
+            Some(parent) => parent.children(site),
+            None => site.children(Some(self.language())),
+        }
+    }
+
+    pub fn prev<'a>(&self, site: &'a Site) -> Option<&'a Model> {
+        Self::before(self.siblings(site), self)
+    }
+
+    pub fn next<'a>(&self, site: &'a Site) -> Option<&'a Model> {
+        Self::after(self.siblings(site), self)
+    }
+
+    /// the previous listed sibling, skipping unlisted pages and drafts
+    pub fn prev_listed<'a>(&self, site: &'a Site) -> Option<&'a Model> {
+        Self::before(self.siblings(site).listed(), self)
+    }
+
+    /// the next listed sibling, skipping unlisted pages and drafts
+    pub fn next_listed<'a>(&self, site: &'a Site) -> Option<&'a Model> {
+        Self::after(self.siblings(site).listed(), self)
+    }
+
+    fn before<'a>(pages: Pages<'a>, page: &Model) -> Option<&'a Model> {
+        let position = pages.iter().position(|sibling| sibling == page)?;
+        position.checked_sub(1).and_then(|n| pages.nth(n))
+    }
+
+    fn after<'a>(pages: Pages<'a>, page: &Model) -> Option<&'a Model> {
+        let position = pages.iter().position(|sibling| sibling == page)?;
+        pages.nth(position + 1)
+    }
+
+    /// the parents up to the top of the site, the closest first like kirby's `parents()`
+    pub fn ancestors<'a>(&self, site: &'a Site) -> Pages<'a> {
+        let mut ancestors = vec![];
+        let mut current = self.parent(site);
+        while let Some(parent) = current {
+            ancestors.push(parent);
+            current = parent.parent(site);
+        }
+        Pages::new(ancestors)
+    }
+
+    /// the level of the page, 1 for the pages at the top of the site
+    pub fn depth(&self) -> usize {
+        self.id()
+            .trim_matches('/')
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .count()
+    }
+
+    /// if the page is a parent of the other page at any level in the same language
+    pub fn is_ancestor_of(&self, other: &Model) -> bool {
+        let id = self.id().trim_matches('/');
+        self.language() == other.language()
+            && !id.is_empty()
+            && other
+                .id()
+                .trim_matches('/')
+                .strip_prefix(id)
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    pub fn is_descendant_of(&self, other: &Model) -> bool {
+        other.is_ancestor_of(self)
+    }
+
+    /// if the other page is the direct parent of the page
+    pub fn is_child_of(&self, other: &Model) -> bool {
+        other.is_ancestor_of(self) && self.depth() == other.depth() + 1
+    }
+
+    /// if the page is the current page
+    pub fn is_active(&self, current: &Model) -> bool {
+        self.language() == current.language()
+            && self.id().trim_matches('/') == current.id().trim_matches('/')
+    }
+
+    /// if the page is the current page or one of its parents, like an open menu
+    pub fn is_open(&self, current: &Model) -> bool {
+        self.is_active(current) || self.is_ancestor_of(current)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct MenuItem {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub active: bool,
+    pub open: bool,
+    pub children: Vec<MenuItem>,
+}
+
+/// Builds nested menus of the listed pages below a page or the site
+pub struct MenuBuilder {
+    parent: Option<String>,
+    language: Option<String>,
+    depth: usize,
+}
+
+impl Default for MenuBuilder {
+    fn default() -> Self {
+        Self {
+            parent: None,
+            language: None,
+            depth: 1,
+        }
+    }
+}
+
+impl MenuBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the id of the page with the pages of the menu, the top of the site by default
+    pub fn parent(&mut self, parent: &str) -> &mut Self {
+        self.parent = Some(parent.trim_matches('/').to_string());
+        self
+    }
+
+    /// the language of the pages, the default language by default
+    pub fn language(&mut self, language: &str) -> &mut Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// the levels of the menu, 1 for only the pages without their children
+    pub fn depth(&mut self, depth: usize) -> &mut Self {
+        self.depth = depth;
+        self
+    }
+
+    /// the menu with the active and open items for the current page, if there is one
+    pub fn build(&self, site: &Site, current: Option<&Model>) -> Vec<MenuItem> {
+        let pages = match self.parent.as_deref() {
+            Some(parent) => {
+                let lang = self
+                    .language
+                    .as_deref()
+                    .or(site.default_language().map(|language| language.code()));
+                match site.page(parent, lang) {
+                    Some(page) => page.children(site),
+                    None => return vec![],
+                }
+            }
+            None => site.children(self.language.as_deref()),
+        };
+        Self::items(site, pages, current, self.depth)
+    }
+
+    fn items(site: &Site, pages: Pages, current: Option<&Model>, depth: usize) -> Vec<MenuItem> {
+        if depth == 0 {
+            return vec![];
+        }
+        pages
+            .listed()
+            .filter(|page| site.is_visible(page))
+            .iter()
+            .map(|page| MenuItem {
+                id: page.id().trim_matches('/').to_string(),
+                title: page.title().to_string(),
+                url: page.url(),
+                active: current.is_some_and(|current| page.is_active(current)),
+                open: current.is_some_and(|current| page.is_open(current)),
+                children: Self::items(site, page.children(site), current, depth - 1),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::model::{ModelBuilder, ModelKind};
+    use crate::cms::site::SiteBuilder;
+
+    fn site() -> Site {
+        let mut site = SiteBuilder::new().build();
+        for (path, num) in [
+            ("blog", "1"),
+            ("blog/first", "1"),
+            ("blog/second", "2"),
+            ("blog/notes", ""),
+            ("blog/second/comments", "1"),
+            ("about", "2"),
+            ("imprint", ""),
+        ] {
+            site.insert(
+                ModelBuilder::new()
+                    .kind(&ModelKind::Page)
+                    .path(path)
+                    .num(num)
+                    .title(path)
+                    .build(),
+            );
+        }
+        site
+    }
+
+    #[test]
+    fn it_navigates_between_pages() {
+        let site = site();
+        let first = site.page("blog/first", None).unwrap();
+        let second = site.page("blog/second", None).unwrap();
+        let notes = site.page("blog/notes", None).unwrap();
+        let comments = site.page("blog/second/comments", None).unwrap();
+        let blog = site.page("blog", None).unwrap();
+
+        assert_eq!(first.siblings(&site).len(), 3);
+        assert_eq!(first.next_listed(&site), Some(second));
+        assert_eq!(second.next_listed(&site), None);
+        assert_eq!(second.next(&site), Some(notes));
+        assert_eq!(second.prev_listed(&site), Some(first));
+        assert_eq!(blog.next_listed(&site).unwrap().id(), "about");
+
+        assert_eq!(comments.ancestors(&site).to_vec(), vec![second, blog]);
+        assert_eq!(comments.depth(), 3);
+        assert!(blog.is_ancestor_of(comments));
+        assert!(comments.is_descendant_of(blog));
+        assert!(!comments.is_child_of(blog));
+        assert!(comments.is_child_of(second));
+        assert!(blog.is_open(comments) && !blog.is_active(comments));
+    }
+
+    #[test]
+    fn it_builds_menus() {
+        let site = site();
+        let current = site.page("blog/second/comments", None);
+
+        let menu = MenuBuilder::new().depth(2).build(&site, current);
+        let ids: Vec<&str> = menu.iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(ids, vec!["blog", "about"]);
+        assert!(menu[0].open && !menu[0].active);
+        assert_eq!(menu[0].children.len(), 2);
+        assert!(menu[0].children[1].open);
+        assert!(menu[0].children[1].children.is_empty());
+
+        let submenu = MenuBuilder::new()
+            .parent("blog/second")
+            .build(&site, current);
+        assert_eq!(submenu.len(), 1);
+        assert!(submenu[0].active);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::cms::deserializer::DeserializeError;
+use crate::cms::model::Model;
+use crate::cms::site::Site;
+use serde::de::DeserializeOwned;
+use std::ops::Deref;
+
+/// A typed page bound to a template, usually derived with `#[derive(KhulanPage)]`.
+pub trait KhulanPage: DeserializeOwned {
+    const TEMPLATE: &'static str;
+
+    fn from_model(model: &Model) -> Result<Self, DeserializeError> {
+        model.deserialize()
+    }
+}
+
+/// A model together with its typed content. Derefs to the typed content.
+#[derive(Debug, Clone)]
+pub struct Page<T: KhulanPage> {
+    model: Model,
+    data: T,
+}
+
+impl<T: KhulanPage> Page<T> {
+    pub fn new(model: &Model) -> Result<Self, DeserializeError> {
+        Ok(Self {
+            model: model.clone(),
+            data: T::from_model(model)?,
+        })
+    }
+
+    /// find the page by path or uuid and check that it uses the template of `T`
+    pub fn find(site: &Site, search: &str) -> Option<Result<Self, DeserializeError>> {
+        site.page(search, None)
+            .filter(|model| model.template() == T::TEMPLATE)
+            .map(Self::new)
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T: KhulanPage> Deref for Page<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::content::Content;
+    use crate::cms::field::Field;
+    use crate::cms::model::{ModelBuilder, ModelKind};
+    use crate::KhulanPage;
+    use maplit::hashmap;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, KhulanPage)]
+    #[template = "post"]
+    struct BlogPost {
+        title: String,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize, KhulanPage)]
+    struct Blog {}
+
+    #[test]
+    fn it_binds_pages_to_templates() {
+        assert_eq!(BlogPost::TEMPLATE, "post");
+        assert_eq!(Blog::TEMPLATE, "blog");
+
+        let model = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("blog/whales")
+            .template("post")
+            .content(&Content::new(Some(&hashmap! {
+                "title".to_string() => Field::new("title", Some("Whales")),
+                "tags".to_string() => Field::new("tags", Some("sea, mammals")),
+            })))
+            .build();
+        let mut site = Site::new(None, None, None);
+        site.models.insert(model.path(), model);
+
+        let page: Page<BlogPost> = Page::find(&site, "blog/whales").unwrap().unwrap();
+        assert_eq!(page.title(), "Whales");
+        assert_eq!(page.tags().len(), 2);
+        assert_eq!(page.model().path(), "blog/whales");
+
+        assert!(Page::<Blog>::find(&site, "blog/whales").is_none());
+    }
+}
@@ -0,0 +1,461 @@
+use crate::cms::model::Model;
+use crate::cms::site::Site;
+use std::cmp::Ordering;
+
+/// A collection of borrowed pages, like `$page->children()` in kirby. Filters, sorting
+/// and pagination consume the collection and change its list of references in place,
+/// so chaining them neither clones a model nor copies the list.
+///
+/// The collection is eager: the references to all matching pages are collected from the
+/// index when it is created, since children are sorted by their folders first and `len`,
+/// `last` and the pagination need every page anyway. So `limit(3)` after a filter still
+/// looks at all pages, only without allocating again.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pages<'a> {
+    pages: Vec<&'a Model>,
+}
+
+impl<'a> Pages<'a> {
+    pub fn new(pages: Vec<&'a Model>) -> Self {
+        Self { pages }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a Model> + '_ {
+        self.pages.iter().copied()
+    }
+
+    pub fn to_vec(&self) -> Vec<&'a Model> {
+        self.pages.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&'a Model> {
+        self.pages.first().copied()
+    }
+
+    pub fn last(&self) -> Option<&'a Model> {
+        self.pages.last().copied()
+    }
+
+    /// the page at the zero-based position
+    pub fn nth(&self, n: usize) -> Option<&'a Model> {
+        self.pages.get(n).copied()
+    }
+
+    pub fn filter<F: Fn(&Model) -> bool>(mut self, predicate: F) -> Self {
+        self.pages.retain(|page| predicate(page));
+        self
+    }
+
+    /// the pages with a num that are not drafts
+    pub fn listed(self) -> Self {
+        self.filter(|page| page.is_listed())
+    }
+
+    pub fn unlisted(self) -> Self {
+        self.filter(|page| page.is_unlisted())
+    }
+
+    /// the pages that are neither drafts nor in drafts
+    pub fn published(self) -> Self {
+        self.filter(|page| page.is_published())
+    }
+
+    pub fn drafts(self) -> Self {
+        self.filter(|page| page.is_draft())
+    }
+
+    /// Filter by the value of a field like kirby's `filterBy`. The operators are
+    /// `==`, `!=`, `*=` (contains), `^=` (starts with), `$=` (ends with), `<`, `>`,
+    /// `<=`, `>=`, `in` and `not in` (comma separated values) and `has` for fields
+    /// with comma separated values like tags. Unknown operators match no page.
+    pub fn filter_by(self, field: &str, operator: &str, value: &str) -> Self {
+        self.filter(|page| {
+            let actual = Self::value(page, field).unwrap_or_default();
+            let list = |text: &str| -> Vec<String> {
+                text.split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            };
+            match operator {
+                "==" | "=" => actual == value,
+                "!=" => actual != value,
+                "*=" => actual.contains(value),
+                "^=" => actual.starts_with(value),
+                "$=" => actual.ends_with(value),
+                "<" => natural_cmp(&actual, value) == Ordering::Less,
+                ">" => natural_cmp(&actual, value) == Ordering::Greater,
+                "<=" => natural_cmp(&actual, value) != Ordering::Greater,
+                ">=" => natural_cmp(&actual, value) != Ordering::Less,
+                "in" => list(value).contains(&actual),
+                "not in" => !list(value).contains(&actual),
+                "has" => list(&actual).iter().any(|item| item == value),
+                _ => false,
+            }
+        })
+    }
+
+    /// Sort by one or more fields like `date desc, title`, with natural sorting
+    /// so `page-2` comes before `page-10`. Pages with equal values keep their order.
+    pub fn sort_by(mut self, fields: &str) -> Self {
+        let fields: Vec<(String, bool)> = fields
+            .split(',')
+            .filter_map(|field| {
+                let mut parts = field.split_whitespace();
+                let name = parts.next()?.to_lowercase();
+                let desc = parts
+                    .next()
+                    .is_some_and(|order| order.eq_ignore_ascii_case("desc"));
+                Some((name, desc))
+            })
+            .collect();
+
+        self.pages.sort_by(|a, b| {
+            fields
+                .iter()
+                .map(|(field, desc)| {
+                    let ordering = natural_cmp(
+                        &Self::value(a, field).unwrap_or_default(),
+                        &Self::value(b, field).unwrap_or_default(),
+                    );
+                    match desc {
+                        true => ordering.reverse(),
+                        false => ordering,
+                    }
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        self
+    }
+
+    /// listed pages by num and then unlisted pages by id, like the folders in kirby
+    pub(crate) fn sort_by_folder(mut self) -> Self {
+        self.pages.sort_by(|a, b| {
+            (a.num().is_none(), a.num())
+                .cmp(&(b.num().is_none(), b.num()))
+                .then_with(|| natural_cmp(a.id(), b.id()))
+        });
+        self
+    }
+
+    /// the pages by the value of the field, in the order the values first appear
+    pub fn group_by(self, field: &str) -> Vec<(String, Pages<'a>)> {
+        let mut groups: Vec<(String, Pages<'a>)> = vec![];
+        for page in self.pages {
+            let value = Self::value(page, field).unwrap_or_default();
+            match groups.iter_mut().find(|(group, _)| *group == value) {
+                Some((_, pages)) => pages.pages.push(page),
+                None => groups.push((value, Pages::new(vec![page]))),
+            }
+        }
+        groups
+    }
+
+    /// without the pages with the ids, uuids or paths
+    pub fn not(self, pages: &[&str]) -> Self {
+        let pages: Vec<&str> = pages.iter().map(|page| page.trim_matches('/')).collect();
+        self.filter(|page| {
+            !pages.contains(&page.id().trim_matches('/'))
+                && !pages.contains(&page.uuid())
+                && !pages.contains(&page.path().as_str())
+        })
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.pages.drain(..offset.min(self.pages.len()));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.pages.truncate(limit);
+        self
+    }
+
+    /// the pages on the one-based page of the pagination, clamped to the last page
+    pub fn paginate(self, limit: usize, page: usize) -> Pagination<'a> {
+        let limit = limit.max(1);
+        let total = self.len();
+        let last = total.div_ceil(limit).max(1);
+        let page = page.clamp(1, last);
+        Pagination {
+            pages: self.offset((page - 1) * limit).limit(limit),
+            page,
+            limit,
+            total,
+        }
+    }
+
+    /// The pages that contain all words of the query in the fields, or in all
+    /// fields if there are none, ordered by the number of matches in the title
+    /// and then in the other fields like kirby's `search`.
+    pub fn search(mut self, query: &str, fields: &[&str]) -> Self {
+        let words: Vec<String> = query
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .collect();
+        if words.is_empty() {
+            return Self::default();
+        }
+        let fields: Vec<String> = fields.iter().map(|field| field.to_lowercase()).collect();
+
+        let mut scored: Vec<(usize, &'a Model)> = self
+            .pages
+            .iter()
+            .filter_map(|&page| {
+                let texts: Vec<(bool, String)> = page
+                    .content()
+                    .fields
+                    .iter()
+                    .filter(|(name, _)| fields.is_empty() || fields.contains(name))
+                    .map(|(name, field)| (name == "title", field.value().to_lowercase()))
+                    .collect();
+                let mut score = 0;
+                for word in words.iter() {
+                    let matches: usize = texts
+                        .iter()
+                        .map(|(title, text)| {
+                            text.matches(word.as_str()).count() * if *title { 10 } else { 1 }
+                        })
+                        .sum();
+                    if matches == 0 {
+                        return None;
+                    }
+                    score += matches;
+                }
+                Some((score, page))
+            })
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        self.pages.clear();
+        self.pages.extend(scored.into_iter().map(|(_, page)| page));
+        self
+    }
+
+    /// the value to filter and sort by, the content field or a property like `num`
+    fn value(page: &Model, field: &str) -> Option<String> {
+        let field = field.to_lowercase();
+        match field.as_str() {
+            "id" => Some(page.id().trim_matches('/').to_string()),
+            "num" => page.num().map(|num| num.to_string()),
+            "slug" => Some(page.slug().to_string()),
+            "template" => Some(page.template().to_string()),
+            "language" => Some(page.language().to_string()),
+            _ => page
+                .content()
+                .fields
+                .get(&field)
+                .map(|value| value.value().to_string()),
+        }
+    }
+}
+
+impl<'a> IntoIterator for Pages<'a> {
+    type Item = &'a Model;
+    type IntoIter = std::vec::IntoIter<&'a Model>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pages.into_iter()
+    }
+}
+
+impl<'a> FromIterator<&'a Model> for Pages<'a> {
+    fn from_iter<I: IntoIterator<Item = &'a Model>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+/// A page of a paginated collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pagination<'a> {
+    pub pages: Pages<'a>,
+    /// the one-based number of the page
+    pub page: usize,
+    pub limit: usize,
+    pub total: usize,
+}
+
+impl Pagination<'_> {
+    pub fn last_page(&self) -> usize {
+        self.total.div_ceil(self.limit).max(1)
+    }
+
+    pub fn has_prev_page(&self) -> bool {
+        self.page > 1
+    }
+
+    pub fn has_next_page(&self) -> bool {
+        self.page < self.last_page()
+    }
+
+    pub fn prev_page(&self) -> Option<usize> {
+        self.has_prev_page().then(|| self.page - 1)
+    }
+
+    pub fn next_page(&self) -> Option<usize> {
+        self.has_next_page().then(|| self.page + 1)
+    }
+}
+
+impl Site {
+    /// the pages at the top of the site in the language, or the default language
+    pub fn children(&self, lang: Option<&str>) -> Pages<'_> {
+        let lang = lang
+            .or(self.default_language().map(|language| language.code()))
+            .unwrap_or("");
+        self.index
+            .children(lang, "")
+            .filter_map(|key| self.models.get(key))
+            .collect::<Pages>()
+            .sort_by_folder()
+    }
+}
+
+/// Compare case insensitive with runs of digits as numbers, so `2` is before `10`
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                        digits.push(*c);
+                        chars.next();
+                    }
+                    digits
+                };
+                let (x, y) = (number(&mut a), number(&mut b));
+                let (x_trimmed, y_trimmed) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::content::Content;
+    use crate::cms::field::Field;
+    use crate::cms::model::{ModelBuilder, ModelKind};
+
+    fn page(path: &str, num: &str, fields: &[(&str, &str)]) -> Model {
+        ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path(path)
+            .num(num)
+            .content(&Content::new(Some(
+                &fields
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), Field::new(name, Some(value))))
+                    .collect(),
+            )))
+            .build()
+    }
+
+    #[test]
+    fn it_sorts_naturally() {
+        assert_eq!(natural_cmp("page-2", "page-10"), Ordering::Less);
+        assert_eq!(natural_cmp("Apple", "banana"), Ordering::Less);
+        assert_eq!(natural_cmp("007", "7"), Ordering::Equal);
+        assert_eq!(natural_cmp("2024-10-01", "2024-09-30"), Ordering::Greater);
+    }
+
+    #[test]
+    fn it_filters_sorts_and_paginates() {
+        let models = [
+            page(
+                "blog/a",
+                "1",
+                &[
+                    ("title", "Rust 2"),
+                    ("tags", "rust, web"),
+                    ("date", "2024-03-01"),
+                ],
+            ),
+            page(
+                "blog/b",
+                "2",
+                &[
+                    ("title", "Whales"),
+                    ("tags", "ocean"),
+                    ("date", "2024-01-01"),
+                ],
+            ),
+            page(
+                "blog/c",
+                "10",
+                &[
+                    ("title", "Rust 10"),
+                    ("tags", "rust"),
+                    ("date", "2024-03-01"),
+                ],
+            ),
+            page("blog/d", "", &[("title", "About rust"), ("tags", "meta")]),
+        ];
+        let pages: Pages = models.iter().collect();
+
+        let listed = pages.clone().listed();
+        assert_eq!(listed.len(), 3);
+        assert_eq!(pages.clone().unlisted().first().unwrap().id(), "blog/d");
+
+        let rust = pages.clone().filter_by("tags", "has", "rust");
+        assert_eq!(rust.len(), 2);
+        let recent = pages.clone().filter_by("date", ">=", "2024-02-01");
+        assert_eq!(recent.len(), 2);
+        assert_eq!(pages.clone().filter_by("num", "in", "1, 2").len(), 2);
+        assert_eq!(pages.clone().filter_by("title", "~", "Rust").len(), 0);
+
+        let sorted = pages.clone().listed().sort_by("date desc, title");
+        let ids: Vec<&str> = sorted.iter().map(|page| page.id()).collect();
+        assert_eq!(ids, vec!["blog/a", "blog/c", "blog/b"]);
+
+        let groups = pages.clone().listed().group_by("date");
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "2024-03-01");
+        assert_eq!(groups[0].1.len(), 2);
+
+        let pagination = pages.clone().paginate(3, 2);
+        assert_eq!(pagination.pages.len(), 1);
+        assert_eq!(pagination.last_page(), 2);
+        assert_eq!(pagination.prev_page(), Some(1));
+        assert_eq!(pagination.next_page(), None);
+        assert_eq!(pages.clone().paginate(3, 9).page, 2);
+
+        assert_eq!(pages.nth(1).unwrap().id(), "blog/b");
+        assert_eq!(pages.last().unwrap().id(), "blog/d");
+        assert_eq!(pages.clone().not(&["blog/a", "/blog/b"]).len(), 2);
+
+        let found = pages.clone().search("rust", &[]);
+        assert_eq!(found.len(), 3);
+        assert_eq!(found.last().unwrap().id(), "blog/d");
+        assert_eq!(pages.search("rust whales", &["title"]).len(), 0);
+    }
+}
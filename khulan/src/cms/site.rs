@@ -1,6 +1,15 @@
+use crate::cms::blueprint::Blueprint;
+use crate::cms::content::Content;
+use crate::cms::index::Index;
+use crate::cms::language::{Language, LanguagePrefix, Languages};
+use crate::cms::metadata::ImageMeta;
 use crate::cms::model::{Model, ModelKind};
+use crate::cms::sitemap::Sitemap;
+use crate::cms::translation::TranslationReport;
+use crate::cms::validation::ValidationReport;
 use crate::database::DatabaseBuilder;
 use fxhash::FxHashMap;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use url::Url;
 
@@ -9,7 +18,52 @@ pub struct Site {
     dir: PathBuf, // TODO: refactor to roots hashmap
     url: Url,
     pub models: FxHashMap<String, Model>,
+    /// the page tree and indexes of the models, kept up to date by `insert` and `remove`
+    pub(crate) index: Index,
+    pub blueprints: FxHashMap<String, Blueprint>,
+    pub languages: Languages,
+    language_prefix: LanguagePrefix,
+    /// the id of the page that is served at the root, like kirby's `home`
+    home: String,
+    /// the id of the page that is rendered when there is no page, like kirby's `error`
+    error: String,
+    /// if drafts are served, like for previews during development
+    drafts: bool,
+    /// if the gps location is removed from the exif data in the metadata of images
+    strip_gps: bool,
+    /// the ids of the pages only served to logged in users, with their children and files
+    members_only: Vec<String>,
+    /// the metadata of the images by their file, read again when they are modified
+    pub(crate) images: FxHashMap<String, ImageMeta>,
+    /// content files with a language suffix that is not a language of the site, by file
+    pub unknown_languages: BTreeMap<String, String>,
+    extensions: Vec<String>,
+    /// translated url paths to the keys of their models
+    routes: FxHashMap<String, String>,
     pub last_modified: std::time::SystemTime,
+    /// the latest modification and the number of files and folders of the accounts when
+    /// the users were loaded, to reload them after changes in the panel
+    pub(crate) accounts: Option<(std::time::SystemTime, usize)>,
+    /// the same for the blueprints, to load them again after they are edited
+    pub(crate) blueprints_state: Option<(std::time::SystemTime, usize)>,
+    /// and for the languages, to load the whole site again after they are edited
+    pub(crate) languages_state: Option<(std::time::SystemTime, usize)>,
+}
+
+/// The result of resolving a request path to a page, or to a file for media paths
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resolved<'a> {
+    Page(&'a Model),
+    /// the path is not the canonical url of the page in the requested language
+    Redirect(&'a Model),
+}
+
+impl<'a> Resolved<'a> {
+    pub fn model(&self) -> &'a Model {
+        match self {
+            Resolved::Page(model) | Resolved::Redirect(model) => model,
+        }
+    }
 }
 
 impl Site {
@@ -18,11 +72,28 @@ impl Site {
         dir: Option<PathBuf>,
         url: Option<Url>,
     ) -> Self {
+        let models = models.unwrap_or_default();
         Self {
             dir: dir.unwrap_or(PathBuf::from("")),
             url: url.unwrap_or(Url::parse("http://localhost:8000").unwrap()),
-            models: models.unwrap_or(FxHashMap::default()),
+            index: Index::from_models(&models),
+            models,
+            blueprints: FxHashMap::default(),
+            languages: Languages::default(),
+            language_prefix: LanguagePrefix::default(),
+            home: "home".to_string(),
+            error: "error".to_string(),
+            drafts: false,
+            strip_gps: false,
+            members_only: vec![],
+            images: FxHashMap::default(),
+            unknown_languages: BTreeMap::new(),
+            extensions: vec!["txt".to_string(), "md".to_string()],
+            routes: FxHashMap::default(),
             last_modified: std::time::SystemTime::UNIX_EPOCH,
+            accounts: None,
+            blueprints_state: None,
+            languages_state: None,
         }
     }
 
@@ -34,6 +105,68 @@ impl Site {
         &self.url
     }
 
+    pub fn language_prefix(&self) -> LanguagePrefix {
+        self.language_prefix
+    }
+
+    pub fn home_id(&self) -> &str {
+        &self.home
+    }
+
+    pub fn error_id(&self) -> &str {
+        &self.error
+    }
+
+    pub fn strips_gps(&self) -> bool {
+        self.strip_gps
+    }
+
+    /// if the page or file is only served to logged in users, like the children of `members`
+    pub fn is_members_only(&self, model: &Model) -> bool {
+        self.is_members_only_id(model.id())
+    }
+
+    pub fn is_members_only_id(&self, id: &str) -> bool {
+        let id = id.trim_matches('/');
+        self.members_only.iter().any(|members_only| {
+            id.strip_prefix(members_only.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+
+    /// if the page can be served, drafts and pages in drafts only if drafts are enabled
+    pub fn is_visible(&self, page: &Model) -> bool {
+        self.drafts || page.is_published()
+    }
+
+    /// extensions of the content files to load and watch
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    /// add or replace the model by its path and update the index
+    pub fn insert(&mut self, model: Model) -> Option<Model> {
+        let key = model.path();
+        let previous = self.models.remove(&key);
+        if let Some(previous) = previous.as_ref() {
+            self.index.remove(&key, previous);
+        }
+        self.index.insert(&key, &model);
+        self.models.insert(key, model);
+        previous
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Model> {
+        let model = self.models.remove(key)?;
+        self.index.remove(key, &model);
+        Some(model)
+    }
+
+    /// rebuild the index after changing the models directly
+    pub fn reindex(&mut self) {
+        self.index = Index::from_models(&self.models);
+    }
+
     pub fn load(&mut self, changes: &Vec<String>) -> bool {
         if !self.models.is_empty() && changes.is_empty() {
             return false;
@@ -43,7 +176,11 @@ impl Site {
 
         // match DatabaseBuilder::new().build().load(self) {
         match database.load(self, changes.clone()) {
-            Ok(_) => true,
+            Ok(_) => {
+                self.merge_translations();
+                self.resolve_urls();
+                true
+            }
             Err(e) => {
                 eprintln!("Error loading database: {}", e); // Print the error to the terminal
                 false
@@ -51,12 +188,386 @@ impl Site {
         }
     }
 
+    /// Merge every translation over the model of the default language, so fields
+    /// that are not translated fall back to the default like in kirby.
+    pub fn merge_translations(&mut self) {
+        let default = match self.default_language() {
+            Some(language) => language.code().to_string(),
+            None => return,
+        };
+
+        let merges: Vec<(String, Option<Content>, Vec<String>)> = self
+            .models
+            .iter()
+            .filter(|(_, model)| !model.language().is_empty() && model.language() != default)
+            .map(|(key, model)| {
+                let source = self
+                    .models
+                    .get(&model.path_in(&default))
+                    .map(|source| source.content().clone());
+                let untranslatable = model
+                    .blueprint(self)
+                    .map(|blueprint| {
+                        blueprint
+                            .fields
+                            .values()
+                            .filter(|field| !field.translate)
+                            .map(|field| field.name.clone())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (key.clone(), source, untranslatable)
+            })
+            .collect();
+
+        for (key, source, untranslatable) in merges {
+            if let Some(model) = self.models.get_mut(&key) {
+                model.merge_translation(source.as_ref(), &untranslatable);
+            }
+        }
+    }
+
+    /// Build the translated paths and urls of all pages from the slugs of each page and its parents
+    pub fn resolve_urls(&mut self) {
+        let default = self
+            .default_language()
+            .map(|language| language.code().to_string())
+            .unwrap_or_default();
+
+        let uris: FxHashMap<String, String> = self
+            .models
+            .iter()
+            .filter(|(_, model)| model.is_page())
+            .map(|(key, model)| (key.clone(), self.uri_of(model, &default)))
+            .collect();
+
+        let mut routes = FxHashMap::default();
+        let mut updates = vec![];
+        for (key, uri) in uris.iter() {
+            let model = &self.models[key];
+            let (_, host, prefix) = self.base_of(model.language());
+            let route = Self::join(&prefix, uri);
+            routes.insert(Self::join(&host, &route), key.clone());
+
+            let languages = match self.languages.is_empty() {
+                true => vec![model.language()],
+                false => self.languages.codes(),
+            };
+            let urls = languages
+                .into_iter()
+                .filter_map(|lang| {
+                    uris.get(&model.path_in(lang)).map(|uri| {
+                        let (base, _, prefix) = self.base_of(lang);
+                        (
+                            lang.to_string(),
+                            format!("{}/{}", base, Self::join(&prefix, uri))
+                                .trim_end_matches('/')
+                                .to_string(),
+                        )
+                    })
+                })
+                .collect();
+            updates.push((key.clone(), uri.clone(), route, urls));
+        }
+
+        for (key, uri, route, urls) in updates {
+            if let Some(model) = self.models.get_mut(&key) {
+                model.set_urls(&uri, &route, urls);
+            }
+        }
+        self.routes = routes;
+    }
+
+    fn uri_of(&self, model: &Model, default: &str) -> String {
+        if model.is_home() {
+            return "".to_string();
+        }
+
+        let segments: Vec<&str> = model.id().split('/').collect();
+        (1..=segments.len())
+            .map(|depth| {
+                let id = segments[..depth].join("/");
+                self.models
+                    .get(&Self::join(model.language(), &id))
+                    .or_else(|| self.models.get(&Self::join(default, &id)))
+                    .map(|ancestor| ancestor.slug())
+                    .unwrap_or(segments[depth - 1])
+                    .to_string()
+            })
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+
+    /// the prefix of the urls in the language
+    fn prefix<'a>(&self, lang: &'a str) -> &'a str {
+        let is_default = self
+            .default_language()
+            .is_some_and(|language| language.code() == lang);
+        match self.language_prefix {
+            LanguagePrefix::NotDefault if is_default => "",
+            _ => lang,
+        }
+    }
+
+    /// The base url, host and prefix of the urls in the language. Like in kirby, the
+    /// url of the language can be a domain like `https://example.de`, a custom prefix
+    /// like `/deutsch` or `/` for none. Otherwise the site url and prefix mode are used.
+    fn base_of(&self, lang: &str) -> (String, String, String) {
+        let site = (
+            self.url.as_str().trim_end_matches('/').to_string(),
+            self.host().to_string(),
+        );
+        let url = self
+            .language(lang)
+            .and_then(|language| language.url.as_deref())
+            .map(|url| url.trim())
+            .filter(|url| !url.is_empty());
+
+        match url.map(|url| (url, Url::parse(url))) {
+            Some((_, Ok(url))) if url.has_host() => (
+                url.origin().ascii_serialization(),
+                url.host_str().unwrap_or("").to_lowercase(),
+                url.path().trim_matches('/').to_string(),
+            ),
+            Some((url, _)) => (site.0, site.1, url.trim_matches('/').to_string()),
+            None => (site.0, site.1, self.prefix(lang).to_string()),
+        }
+    }
+
+    /// the host of the site url, like `getkhulan.com`
+    pub fn host(&self) -> &str {
+        self.url.host_str().unwrap_or("")
+    }
+
+    /// The language that owns a domain, like `de` for `example.de`, if it is the
+    /// only language on the domain and its urls have no prefix.
+    pub fn language_of_host(&self, host: &str) -> Option<&Language> {
+        let host = Self::domain(host);
+        let mut languages = self
+            .languages
+            .iter()
+            .map(|language| (language, self.base_of(language.code())))
+            .filter(|(_, (_, language_host, _))| *language_host == host);
+        match (languages.next(), languages.next()) {
+            (Some((language, (_, _, prefix))), None) if prefix.is_empty() => Some(language),
+            _ => None,
+        }
+    }
+
+    /// the host as used in the routes. unknown hosts like `127.0.0.1` are the site itself
+    pub(crate) fn route_host(&self, host: Option<&str>) -> String {
+        let host = host.map(Self::domain).unwrap_or_default();
+        let known = self
+            .languages
+            .iter()
+            .any(|language| self.base_of(language.code()).1 == host);
+        match known {
+            true => host,
+            false => self.host().to_string(),
+        }
+    }
+
+    /// the host without port, like in the `Host` header
+    fn domain(host: &str) -> String {
+        host.rsplit_once(':')
+            .filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit()))
+            .map_or(host, |(domain, _)| domain)
+            .to_lowercase()
+    }
+
+    fn join(lang: &str, path: &str) -> String {
+        format!("{}/{}", lang, path).trim_matches('/').to_string()
+    }
+
+    /// Resolve a request path with translated slugs to a page on the site url.
+    pub fn resolve(&self, search: &str) -> Option<Resolved<'_>> {
+        self.resolve_at(None, search)
+    }
+
+    /// Resolve a request path with translated slugs to a page on one of the domains of the
+    /// site. Paths that use the slugs of another language, the folder names or the domain
+    /// of another language redirect to the canonical url.
+    pub fn resolve_at(&self, host: Option<&str>, search: &str) -> Option<Resolved<'_>> {
+        let search = search.replace("+", "/").trim_matches('/').to_string();
+        let host = self.route_host(host);
+
+        if let Some(model) = self
+            .routes
+            .get(&Self::join(&host, &search))
+            .and_then(|key| self.models.get(key))
+        {
+            return Some(Resolved::Page(model));
+        }
+
+        if let Some(model) = self.models.get(&search).filter(|model| model.is_page()) {
+            let (_, model_host, _) = self.base_of(model.language());
+            let canonical = Self::join(&model_host, &model.url_path());
+            return match canonical == Self::join(&host, &search)
+                || !self.routes.contains_key(&canonical)
+            {
+                true => Some(Resolved::Page(model)),
+                false => Some(Resolved::Redirect(model)),
+            };
+        }
+
+        // the slugs of another translation on the domain or with the prefix of the requested language
+        let requested = match self.language_of_host(&host) {
+            Some(language) => Some((language.code(), search.as_str())),
+            None => search
+                .split_once('/')
+                .filter(|(lang, _)| self.languages.contains(lang)),
+        };
+        if let Some((lang, uri)) = requested {
+            let translation = self.routes.iter().find_map(|(route, key)| {
+                let model = self.models.get(key)?;
+                let (_, model_host, prefix) = self.base_of(model.language());
+                if model.language() == lang
+                    || Self::join(&model_host, &Self::join(&prefix, uri)) != *route
+                {
+                    return None;
+                }
+                self.models.get(&model.path_in(lang))
+            });
+            if let Some(translation) = translation {
+                return Some(Resolved::Redirect(translation));
+            }
+        }
+
+        self.page(&search, None).map(Resolved::Page)
+    }
+
+    /// The page a request to a path without language prefix on the site url
+    /// should be redirected to, in the language the visitor prefers.
+    pub fn negotiate(&self, search: &str, preferred: Option<&str>) -> Option<&Model> {
+        self.negotiate_at(None, search, preferred)
+    }
+
+    /// The page a request to a path without language prefix should be redirected to,
+    /// in the language the visitor prefers. Without a preference, the default
+    /// language is used if all urls need a prefix. Domains of a single language and
+    /// languages on other domains are never negotiated.
+    pub fn negotiate_at(
+        &self,
+        host: Option<&str>,
+        search: &str,
+        preferred: Option<&str>,
+    ) -> Option<&Model> {
+        let search = search.replace("+", "/").trim_matches('/').to_string();
+        let first = search.split('/').next().unwrap_or("");
+        let host = self.route_host(host);
+        if self.languages.is_empty()
+            || self.languages.contains(first)
+            || self.language_of_host(&host).is_some()
+        {
+            return None;
+        }
+        let on_host = |lang: &&str| self.base_of(lang).1 == host;
+        let default = self.default_language()?.code();
+        let preferred = preferred
+            .filter(|lang| self.languages.contains(lang))
+            .filter(on_host);
+
+        // the path with the prefix of the language, which may use the slugs of another
+        let resolve = |lang: &str| {
+            let (_, _, prefix) = self.base_of(lang);
+            self.resolve_at(Some(&host), &Self::join(&prefix, &search))
+        };
+        let target = match self.language_prefix {
+            LanguagePrefix::All => preferred
+                .and_then(resolve)
+                .or_else(|| Some(default).filter(on_host).and_then(resolve))
+                .map(|resolved| resolved.model()),
+            LanguagePrefix::NotDefault => {
+                let lang = preferred.filter(|lang| *lang != default)?;
+                let page = self.resolve_at(Some(&host), &search)?.model();
+                page.translation(self, lang)
+            }
+        };
+
+        // never redirect to the requested url itself or to drafts
+        target
+            .filter(|model| self.is_visible(model))
+            .filter(|model| {
+                let (_, model_host, _) = self.base_of(model.language());
+                Self::join(&model_host, &model.url_path()) != Self::join(&host, &search)
+            })
+    }
+
     pub fn changes(&self) -> Vec<String> {
         let database = DatabaseBuilder::new().build();
 
         database.changes(self)
     }
 
+    /// the folder with the content and files of the pages
+    pub fn content_dir(&self) -> PathBuf {
+        DatabaseBuilder::new().build().content_dir(self)
+    }
+
+    /// the folder with the generated files like thumbs
+    pub fn media_dir(&self) -> PathBuf {
+        DatabaseBuilder::new().build().media_dir(self)
+    }
+
+    /// Resolve a media path like `pages/blog/whales/<hash>/whale.jpg` or `site/<hash>/logo.svg`
+    /// to its file. The hash changes with the file, so old urls redirect to the current one.
+    pub fn resolve_media(&self, search: &str) -> Option<Resolved<'_>> {
+        let (id, hash, filename) = media_parts(search)?;
+        // the metadata and content files are never served
+        let extension = filename.rsplit_once('.').map(|(_, extension)| extension);
+        if extension.is_some_and(|extension| self.extensions.iter().any(|e| e == extension)) {
+            return None;
+        }
+
+        let file = self
+            .file(&format!("{}/{}", id, filename), None)
+            .filter(|file| self.is_visible(file))?;
+        match file.media_hash().as_deref() == Some(hash) {
+            true => Some(Resolved::Page(file)),
+            false => Some(Resolved::Redirect(file)),
+        }
+    }
+
+    /// the id of the page of a media path, also for the thumbs of its images
+    pub fn media_page_id(&self, search: &str) -> Option<String> {
+        media_parts(search).map(|(id, _, _)| id)
+    }
+
+    pub fn blueprint(&self, name: &str) -> Option<&Blueprint> {
+        self.blueprints.get(name)
+    }
+
+    pub fn language(&self, code: &str) -> Option<&Language> {
+        self.languages.get(code)
+    }
+
+    pub fn default_language(&self) -> Option<&Language> {
+        self.languages.default_language()
+    }
+
+    /// the languages the page has a content file for, with the default first
+    pub fn languages_of(&self, page: &Model) -> Vec<&Language> {
+        self.languages
+            .iter()
+            .filter(|language| self.models.contains_key(&page.path_in(language.code())))
+            .collect()
+    }
+
+    /// the pages with urls on the host, with the urls of their translations
+    pub fn sitemap(&self, host: Option<&str>) -> Sitemap {
+        Sitemap::from_site(self, host)
+    }
+
+    /// the pages with missing, untranslated or outdated translations by language
+    pub fn translation_report(&self) -> TranslationReport {
+        TranslationReport::from_site(self)
+    }
+
+    /// validate the content of all models against the blueprints of their templates
+    pub fn validate(&self) -> ValidationReport {
+        ValidationReport::from_site(self)
+    }
+
     pub fn model(&self, lang: Option<&str>) -> Option<&Model> {
         match lang {
             Some(lang) => self.models.get(format!("{lang}/$").as_str()).or_else(|| {
@@ -77,35 +588,123 @@ impl Site {
         let search = search.replace("+", "/").trim_matches('/').to_string();
         // println!("search: {}", search);
         // println!("models: {:?}", self.models.keys());
+        if let Some(model) = self
+            .routes
+            .get(&Self::join(self.host(), &search))
+            .and_then(|key| self.models.get(key))
+            .filter(|model| lang.is_none_or(|lang| model.language() == lang))
+        {
+            return Some(model);
+        }
         match lang {
-            Some(lang) => self.models.get(&search).or_else(|| {
-                self.models.values().find(|model| {
-                    model.language() == lang
-                        && *model.kind() == ModelKind::Page
-                        && (model.path() == search || model.uuid() == search)
-                })
-            }),
-            None => self.models.get(&search).or_else(|| {
-                self.models.values().find(|model| {
-                    *model.kind() == ModelKind::Page
-                        && (model.path() == search || model.uuid() == search)
-                })
-            }),
+            Some(lang) => self
+                .find(&search)
+                .filter(|model| model.language() == lang)
+                .or_else(|| self.page_by_uuid(&search, Some(lang))),
+            None => self
+                .find(&search)
+                .or_else(|| self.page_by_uuid(&search, None)),
         }
     }
 
+    /// the file by its id like `blog/whales/whale.jpg`, or its name for the files of the
+    /// site, in the language or else the default language
+    pub fn file(&self, id: &str, lang: Option<&str>) -> Option<&Model> {
+        let id = id.trim_matches('/');
+        let (page, filename) = id.rsplit_once('/').unwrap_or(("", id));
+        let lang = lang
+            .or(self.default_language().map(|language| language.code()))
+            .unwrap_or("");
+        self.index
+            .files(lang, page)
+            .filter_map(|key| self.models.get(key))
+            .find(|file| file.filename().as_deref() == Some(filename))
+    }
+
+    /// the page with the uuid in the language, or in the default language if none is given
+    pub fn page_by_uuid(&self, uuid: &str, lang: Option<&str>) -> Option<&Model> {
+        let id = self.index.uuid(uuid)?;
+        let lang = lang
+            .or(self.default_language().map(|language| language.code()))
+            .unwrap_or("");
+        self.index
+            .key(lang, id)
+            .and_then(|key| self.models.get(key))
+    }
+
+    /// the pages with the template in all languages
+    pub fn pages_with_template(&self, template: &str) -> Vec<&Model> {
+        self.index
+            .template(template)
+            .filter_map(|key| self.models.get(key))
+            .collect()
+    }
+
+    /// the pages in the language, or of a single language site with `""`
+    pub fn pages_in(&self, lang: &str) -> Vec<&Model> {
+        self.index
+            .language(lang)
+            .filter_map(|key| self.models.get(key))
+            .collect()
+    }
+
+    /// the home page in the language, or in the default language if there is none
+    pub fn home_page(&self, lang: Option<&str>) -> Option<&Model> {
+        self.page_with_id(&self.home, lang)
+    }
+
+    /// the error page in the language, or in the default language if there is none
+    pub fn error_page(&self, lang: Option<&str>) -> Option<&Model> {
+        self.page_with_id(&self.error, lang)
+    }
+
+    fn page_with_id(&self, id: &str, lang: Option<&str>) -> Option<&Model> {
+        let default = self.default_language().map(|language| language.code());
+        let page = |lang: &str| {
+            self.index
+                .key(lang, id)
+                .and_then(|key| self.models.get(key))
+        };
+        lang.or(default)
+            .and_then(page)
+            .or_else(|| default.and_then(page))
+            .or_else(|| page(""))
+    }
+
     pub fn find(&self, search: &str) -> Option<&Model> {
         let search = search.trim_matches('/');
         self.models
             .get(search)
-            .or_else(|| self.models.values().find(|model| model.path() == search))
+            .or_else(|| self.index.path(search).and_then(|key| self.models.get(key)))
     }
 }
 
+/// the id of the page, the hash and the filename of a media path, with an empty id for the site
+fn media_parts(search: &str) -> Option<(String, &str, &str)> {
+    let segments: Vec<&str> = search.trim_matches('/').split('/').collect();
+    let (filename, hash, parent) = match segments.as_slice() {
+        [parent @ .., hash, filename] if !parent.is_empty() => (*filename, *hash, parent),
+        _ => return None,
+    };
+    let id = match parent {
+        ["site"] => "".to_string(),
+        ["pages", id @ ..] if !id.is_empty() => id.join("/"),
+        _ => return None,
+    };
+    Some((id, hash, filename))
+}
+
 pub struct SiteBuilder {
     dir: PathBuf,
     url: Url,
     models: FxHashMap<String, Model>,
+    extensions: Vec<String>,
+    language_prefix: LanguagePrefix,
+    home: String,
+    error: String,
+    drafts: bool,
+    strip_gps: bool,
+    members_only: Vec<String>,
 }
 
 impl SiteBuilder {
@@ -114,6 +713,13 @@ impl SiteBuilder {
             dir: PathBuf::new(),
             url: Url::parse("http://localhost:8000").unwrap(),
             models: FxHashMap::default(),
+            extensions: vec!["txt".to_string(), "md".to_string()],
+            language_prefix: LanguagePrefix::default(),
+            home: "home".to_string(),
+            error: "error".to_string(),
+            drafts: false,
+            strip_gps: false,
+            members_only: vec![],
         }
     }
 
@@ -132,18 +738,83 @@ impl SiteBuilder {
         self
     }
 
+    pub fn extensions(&mut self, extensions: &[&str]) -> &mut Self {
+        self.extensions = extensions.iter().map(|ext| ext.to_string()).collect();
+        self
+    }
+
+    pub fn language_prefix(&mut self, language_prefix: LanguagePrefix) -> &mut Self {
+        self.language_prefix = language_prefix;
+        self
+    }
+
+    /// the id of the page served at the root of the site, `home` by default
+    pub fn home(&mut self, home: &str) -> &mut Self {
+        self.home = home.trim_matches('/').to_string();
+        self
+    }
+
+    /// the id of the page rendered for paths without a page, `error` by default
+    pub fn error(&mut self, error: &str) -> &mut Self {
+        self.error = error.trim_matches('/').to_string();
+        self
+    }
+
+    /// serve drafts and the pages in them, which are not found by default
+    pub fn drafts(&mut self, drafts: bool) -> &mut Self {
+        self.drafts = drafts;
+        self
+    }
+
+    /// Remove the gps location from the exif data in the metadata of images, like in
+    /// `/api/files`. Off by default, so the metadata has the location of photos. The
+    /// originals under `/media` are served untouched with their exif data, only thumbs
+    /// are encoded without it.
+    pub fn strip_gps(&mut self, strip_gps: bool) -> &mut Self {
+        self.strip_gps = strip_gps;
+        self
+    }
+
+    /// the ids of the pages only served to logged in users, with their children and files
+    pub fn members_only(&mut self, ids: &[&str]) -> &mut Self {
+        self.members_only = ids
+            .iter()
+            .map(|id| id.trim_matches('/').to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+        self
+    }
+
     pub fn build(&self) -> Site {
         Site {
             dir: self.dir.clone(),
             url: self.url.clone(),
             models: self.models.clone(),
+            index: Index::from_models(&self.models),
+            blueprints: FxHashMap::default(),
+            languages: Languages::default(),
+            language_prefix: self.language_prefix,
+            home: self.home.clone(),
+            error: self.error.clone(),
+            drafts: self.drafts,
+            strip_gps: self.strip_gps,
+            members_only: self.members_only.clone(),
+            images: FxHashMap::default(),
+            unknown_languages: BTreeMap::new(),
+            extensions: self.extensions.clone(),
+            routes: FxHashMap::default(),
             last_modified: std::time::SystemTime::UNIX_EPOCH,
+            accounts: None,
+            blueprints_state: None,
+            languages_state: None,
         }
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cms::blueprint::FieldBlueprint;
+    use crate::cms::field::Field;
     use crate::cms::model::ModelBuilder;
     use maplit::hashmap;
 
@@ -157,9 +828,13 @@ mod tests {
             .template("default")
             .build();
         let site = SiteBuilder::new()
-            .models(hashmap! {
-                "1234".to_string() => model
-            })
+            .models(
+                hashmap! {
+                    "1234".to_string() => model
+                }
+                .into_iter()
+                .collect(),
+            )
             .build();
         assert_eq!(site.models.len(), 1);
     }
@@ -176,9 +851,13 @@ mod tests {
             .language("en")
             .build();
         let site = SiteBuilder::new()
-            .models(hashmap! {
-                "123.4".to_string() => model
-            })
+            .models(
+                hashmap! {
+                    "123.4".to_string() => model
+                }
+                .into_iter()
+                .collect(),
+            )
             .build();
         let page = site.page("en", None);
         assert_eq!(page.unwrap().uuid(), "1234");
@@ -194,9 +873,13 @@ mod tests {
             .template("default")
             .build();
         let mut site = SiteBuilder::new()
-            .models(hashmap! {
-                "1234".to_string() => model
-            })
+            .models(
+                hashmap! {
+                    "1234".to_string() => model
+                }
+                .into_iter()
+                .collect(),
+            )
             .build();
 
         let model = ModelBuilder::new()
@@ -216,28 +899,464 @@ mod tests {
     #[cfg(feature = "kirby")]
     fn it_loads_from_kirby() {
         let mut site = SiteBuilder::new()
-            .dir(&PathBuf::from("/Users/bnomei/Sites/getkhulan-com"))
+            .dir(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(".."))
             .build();
         assert_eq!(site.load(&vec![]), true);
         assert_eq!(site.models.len() > 0, true);
         // println!("{:?}", site.models);
     }
 
+    #[test]
+    fn it_falls_back_to_the_default_language() {
+        let mut site = SiteBuilder::new().build();
+        let mut de = Language::new("de");
+        de.default = false;
+        let mut en = Language::new("en");
+        en.default = true;
+        site.languages = Languages::new(vec![de, en]);
+
+        let mut author = FieldBlueprint::default();
+        author.name = "author".to_string();
+        let mut date = FieldBlueprint::default();
+        date.name = "date".to_string();
+        date.translate = false;
+        let mut blueprint = Blueprint::default();
+        blueprint.fields.insert("author".to_string(), author);
+        blueprint.fields.insert("date".to_string(), date);
+        site.blueprints.insert("pages/post".to_string(), blueprint);
+
+        let source = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("/blog/whales")
+            .template("post")
+            .language("en")
+            .title("Whales")
+            .content(&Content::new(Some(
+                &hashmap! {
+                    "date".to_string() => Field::new("date", Some("2024-10-17")),
+                    "author".to_string() => Field::new("author", Some("Bruno")),
+                }
+                .into_iter()
+                .collect(),
+            )))
+            .build();
+        let translation = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("/blog/whales")
+            .template("post")
+            .language("de")
+            .title("Wale")
+            .content(&Content::new(Some(
+                &hashmap! {
+                    "author".to_string() => Field::new("author", Some("Brunhilde")),
+                }
+                .into_iter()
+                .collect(),
+            )))
+            .build();
+        site.models.insert(source.path(), source);
+        site.models.insert(translation.path(), translation);
+
+        site.merge_translations();
+        let translation = site.page("de/blog/whales", None).unwrap();
+        assert_eq!(translation.title(), "Wale");
+        assert!(translation.is_translated("title"));
+        assert_eq!(
+            translation.content().fields.get("date").unwrap().value(),
+            "2024-10-17"
+        );
+        assert!(!translation.is_translated("date"));
+        assert_eq!(
+            translation.content().fields.get("author").unwrap().value(),
+            "Brunhilde"
+        );
+
+        // merging again after the default changed does not keep stale fallbacks
+        let source = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("/blog/whales")
+            .template("post")
+            .language("en")
+            .title("Whales")
+            .build();
+        site.models.insert(source.path(), source);
+        site.merge_translations();
+        let translation = site.page("de/blog/whales", None).unwrap();
+        assert!(translation.content().fields.get("date").is_none());
+    }
+
+    /// a blog in english and german with the translated slug `wale`
+    fn translated_site(language_prefix: LanguagePrefix) -> Site {
+        let mut site = SiteBuilder::new()
+            .url(&Url::parse("https://getkhulan.com").unwrap())
+            .language_prefix(language_prefix)
+            .build();
+        let mut en = Language::new("en");
+        en.default = true;
+        site.languages = Languages::new(vec![en, Language::new("de")]);
+
+        let pages = vec![
+            ("en", "blog", None),
+            ("en", "blog/whales", None),
+            ("de", "blog/whales", Some("wale")),
+            ("en", "home", None),
+        ];
+        for (lang, path, slug) in pages {
+            let mut builder = ModelBuilder::new();
+            builder.kind(&ModelKind::Page).path(path).language(lang);
+            if let Some(slug) = slug {
+                builder.content(&Content::new(Some(
+                    &hashmap! { "slug".to_string() => Field::new("slug", Some(slug)) }
+                        .into_iter()
+                        .collect(),
+                )));
+            }
+            let model = builder.build();
+            site.models.insert(model.path(), model);
+        }
+        site.merge_translations();
+        site.resolve_urls();
+        site
+    }
+
+    #[test]
+    fn it_resolves_translated_slugs() {
+        let site = translated_site(LanguagePrefix::All);
+
+        let whales = site.models.get("en/blog/whales").unwrap();
+        assert_eq!(whales.uri(), "blog/whales");
+        assert_eq!(whales.url(), "https://getkhulan.com/en/blog/whales");
+        assert_eq!(
+            whales.url_for("de"),
+            Some("https://getkhulan.com/de/blog/wale")
+        );
+        assert_eq!(
+            site.models.get("en").unwrap().url(),
+            "https://getkhulan.com/en"
+        );
+
+        let wale = site.models.get("de/blog/whales").unwrap();
+        assert_eq!(wale.slug(), "wale");
+        assert_eq!(site.resolve("de/blog/wale"), Some(Resolved::Page(wale)));
+        assert_eq!(site.page("de/blog/wale", None), Some(wale));
+        // folder name and slugs of other languages redirect to the translation
+        assert_eq!(
+            site.resolve("de/blog/whales"),
+            Some(Resolved::Redirect(wale))
+        );
+        assert_eq!(
+            site.resolve("en/blog/wale"),
+            Some(Resolved::Redirect(whales))
+        );
+        assert_eq!(site.resolve("de/blog/nothing"), None);
+    }
+
+    #[test]
+    fn it_negotiates_paths_without_language() {
+        let site = translated_site(LanguagePrefix::All);
+        let wale = site.models.get("de/blog/whales").unwrap();
+        let whales = site.models.get("en/blog/whales").unwrap();
+        assert_eq!(site.negotiate("blog/whales", Some("de")), Some(wale));
+        assert_eq!(site.negotiate("blog/whales", Some("fr")), Some(whales));
+        assert_eq!(site.negotiate("blog/whales", None), Some(whales));
+        // there is no german home
+        assert_eq!(site.negotiate("", Some("de")), site.models.get("en"));
+        assert_eq!(site.negotiate("", None), site.models.get("en"));
+        assert_eq!(site.negotiate("de/blog/wale", Some("en")), None);
+        assert_eq!(site.page("en/blog/whales", Some("de")), None);
+        assert_eq!(site.page("", None), None);
+
+        let site = translated_site(LanguagePrefix::NotDefault);
+        let wale = site.models.get("de/blog/whales").unwrap();
+        let whales = site.models.get("en/blog/whales").unwrap();
+        assert_eq!(whales.url(), "https://getkhulan.com/blog/whales");
+        assert_eq!(wale.url(), "https://getkhulan.com/de/blog/wale");
+        assert_eq!(site.resolve("blog/whales"), Some(Resolved::Page(whales)));
+        assert_eq!(site.resolve(""), site.models.get("en").map(Resolved::Page));
+        assert_eq!(
+            site.resolve("en/blog/whales"),
+            Some(Resolved::Redirect(whales))
+        );
+        assert_eq!(site.resolve("de/blog/wale"), Some(Resolved::Page(wale)));
+        assert_eq!(site.negotiate("blog/whales", Some("de")), Some(wale));
+        assert_eq!(site.negotiate("blog/whales", Some("en")), None);
+        assert_eq!(site.negotiate("blog/whales", None), None);
+    }
+
+    #[test]
+    fn it_resolves_language_domains() {
+        let mut site = translated_site(LanguagePrefix::All);
+        site.url = Url::parse("https://example.com").unwrap();
+        let mut en = Language::new("en");
+        en.default = true;
+        en.url = Some("https://example.com".to_string());
+        let mut de = Language::new("de");
+        de.url = Some("https://example.de/".to_string());
+        site.languages = Languages::new(vec![en, de]);
+        site.resolve_urls();
+
+        let whales = site.models.get("en/blog/whales").unwrap();
+        let wale = site.models.get("de/blog/whales").unwrap();
+        assert_eq!(whales.url(), "https://example.com/blog/whales");
+        assert_eq!(wale.url(), "https://example.de/blog/wale");
+        assert_eq!(wale.origin(), "https://example.de");
+        assert_eq!(
+            site.language_of_host("example.de:443").unwrap().code(),
+            "de"
+        );
+        assert_eq!(site.language_of_host("localhost"), None);
+
+        let at = |host: &str, path: &str| site.resolve_at(Some(host), path);
+        assert_eq!(at("example.de", "blog/wale"), Some(Resolved::Page(wale)));
+        assert_eq!(
+            at("example.de", "blog/whales"),
+            Some(Resolved::Redirect(wale))
+        );
+        assert_eq!(
+            at("example.com", "blog/whales"),
+            Some(Resolved::Page(whales))
+        );
+        assert_eq!(
+            at("example.com", "blog/wale"),
+            Some(Resolved::Redirect(whales))
+        );
+        // unknown hosts, like in development, are the site url
+        assert_eq!(at("localhost", "blog/whales"), Some(Resolved::Page(whales)));
+        assert_eq!(
+            site.resolve("de/blog/whales"),
+            Some(Resolved::Redirect(wale))
+        );
+        assert_eq!(site.negotiate_at(Some("example.de"), "", Some("en")), None);
+        assert_eq!(site.negotiate_at(Some("example.com"), "", Some("de")), None);
+
+        let sitemap = site.sitemap(Some("example.de"));
+        assert_eq!(sitemap.entries.len(), 1);
+        assert_eq!(sitemap.entries[0].loc, "https://example.de/blog/wale");
+        let xml = sitemap.to_xml();
+        assert!(xml.contains(
+            r#"<xhtml:link rel="alternate" hreflang="x-default" href="https://example.com/blog/whales"/>"#
+        ));
+        assert_eq!(site.sitemap(None).entries.len(), 3);
+        assert_eq!(
+            wale.hreflang(&site),
+            [
+                r#"<link rel="alternate" hreflang="en" href="https://example.com/blog/whales">"#,
+                r#"<link rel="alternate" hreflang="de" href="https://example.de/blog/wale">"#,
+                r#"<link rel="alternate" hreflang="x-default" href="https://example.com/blog/whales">"#,
+            ]
+            .join("\n")
+        );
+        assert_eq!(site.models.get("en/blog").unwrap().hreflang(&site), "");
+    }
+
+    #[test]
+    fn it_has_configurable_home_and_error_pages() {
+        let mut site = SiteBuilder::new().home("start").error("not-found").build();
+        let mut en = Language::new("en");
+        en.default = true;
+        site.languages = Languages::new(vec![en, Language::new("de")]);
+        for (lang, path) in [
+            ("en", "start"),
+            ("en", "home"),
+            ("en", "not-found"),
+            ("de", "not-found"),
+        ] {
+            let mut model = ModelBuilder::new()
+                .kind(&ModelKind::Page)
+                .path(path)
+                .language(lang)
+                .build();
+            model.set_home(model.id() == site.home_id());
+            site.insert(model);
+        }
+        site.resolve_urls();
+
+        assert_eq!(site.home_page(None).unwrap().id(), "start");
+        assert_eq!(site.home_page(Some("de")).unwrap().language(), "en");
+        assert_eq!(site.resolve("en").unwrap().model().id(), "start");
+        assert_eq!(site.resolve("en/home").unwrap().model().id(), "home");
+        assert_eq!(site.error_page(Some("de")).unwrap().language(), "de");
+        assert_eq!(site.error_page(None).unwrap().language(), "en");
+        assert!(site
+            .sitemap(None)
+            .entries
+            .iter()
+            .all(|entry| !entry.loc.contains("not-found")));
+    }
+
+    #[test]
+    fn it_has_members_only_pages() {
+        let mut site = SiteBuilder::new().members_only(&["/members/"]).build();
+        for path in ["members", "members/news", "members-area", "blog"] {
+            site.insert(
+                ModelBuilder::new()
+                    .kind(&ModelKind::Page)
+                    .path(path)
+                    .build(),
+            );
+        }
+        site.resolve_urls();
+
+        assert!(site.is_members_only(site.page("members", None).unwrap()));
+        assert!(site.is_members_only_id("members/news/photo.jpg"));
+        assert!(!site.is_members_only(site.page("members-area", None).unwrap()));
+        assert!(!site.is_members_only_id("blog"));
+        assert_eq!(
+            site.media_page_id("pages/members/news/a1b2c3-1/photo-300x.jpg"),
+            Some("members/news".to_string())
+        );
+        assert_eq!(site.sitemap(None).entries.len(), 2);
+    }
+
+    #[test]
+    fn it_groups_translations() {
+        let mut site = Site::new(None, None, None);
+        let mut en = Language::new("en");
+        en.default = true;
+        site.languages = Languages::new(vec![Language::new("de"), en, Language::new("fr")]);
+        for (kind, lang, path, root) in [
+            (
+                ModelKind::Page,
+                "en",
+                "blog/whales",
+                "blog/1_whales/post.en.txt",
+            ),
+            (
+                ModelKind::Page,
+                "de",
+                "blog/whales",
+                "blog/1_whales/post.de.txt",
+            ),
+            (ModelKind::Page, "en", "blog", "blog/blog.en.txt"),
+            (
+                ModelKind::File,
+                "en",
+                "blog/whales",
+                "blog/1_whales/whale.jpg.en.txt",
+            ),
+            (
+                ModelKind::File,
+                "fr",
+                "blog/whales",
+                "blog/1_whales/whale.jpg.fr.txt",
+            ),
+        ] {
+            let model = ModelBuilder::new()
+                .kind(&kind)
+                .language(lang)
+                .path(path)
+                .root(root)
+                .build();
+            site.models.insert(model.path(), model);
+        }
+
+        let whales = site.models.get("de/blog/whales").unwrap();
+        assert_eq!(whales.identity(), "blog/whales");
+        let translations = whales.translations(&site);
+        assert_eq!(translations.len(), 2);
+        assert_eq!(translations[0].language(), "en");
+        assert_eq!(translations[1], whales);
+        assert_eq!(
+            whales.translation(&site, "en").unwrap().identity(),
+            whales.identity()
+        );
+        assert_eq!(whales.translation(&site, "fr"), None);
+        let codes: Vec<&str> = site.languages_of(whales).iter().map(|l| l.code()).collect();
+        assert_eq!(codes, vec!["en", "de"]);
+
+        let image = site.models.get("fr/blog/whales/whale.jpg.fr.txt").unwrap();
+        assert_eq!(image.identity(), "blog/whales/whale.jpg.txt");
+        let codes: Vec<&str> = site.languages_of(image).iter().map(|l| l.code()).collect();
+        assert_eq!(codes, vec!["en", "fr"]);
+
+        let blog = site.models.get("en/blog").unwrap();
+        assert_eq!(blog.translations(&site), vec![blog]);
+    }
+
     #[test]
     fn it_can_have_a_parent_and_children() {
         let mut site = SiteBuilder::new().build();
 
-        let parent = ModelBuilder::new().path("/parent").build();
+        let parent = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("/parent")
+            .build();
 
-        let child = ModelBuilder::new().path("/parent/child").build();
+        let child = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("/parent/child")
+            .build();
 
-        site.models.insert(parent.path(), parent.clone());
-        site.models.insert(child.path(), child.clone());
+        // not a child, even if its path starts with the path of the parent
+        let sibling = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("/parent-archive")
+            .build();
+
+        site.insert(parent.clone());
+        site.insert(child.clone());
+        site.insert(sibling.clone());
 
         let find_parent = child.parent(&site);
         assert_eq!(find_parent.unwrap().uuid(), Some(&parent).unwrap().uuid());
+        assert_eq!(find_parent, Some(&parent));
+        assert_eq!(sibling.parent(&site), None);
 
         let find_children = parent.children(&site);
         assert_eq!(find_children.len(), 1);
+        assert_eq!(find_children.to_vec(), vec![&child]);
+        assert_eq!(site.children(None).len(), 2);
+    }
+
+    #[test]
+    fn it_indexes_pages_incrementally() {
+        let mut site = SiteBuilder::new().build();
+        let mut en = Language::new("en");
+        en.default = true;
+        site.languages = Languages::new(vec![en, Language::new("de")]);
+
+        let page = |lang: &str, path: &str, num: &str, template: &str, uuid: &str| {
+            let mut builder = ModelBuilder::new();
+            builder
+                .kind(&ModelKind::Page)
+                .language(lang)
+                .path(path)
+                .num(num)
+                .template(template);
+            if !uuid.is_empty() {
+                builder.uuid(uuid);
+            }
+            builder.build()
+        };
+        site.insert(page("en", "blog", "", "blog", "blog-uuid"));
+        site.insert(page("en", "blog/whales", "2", "post", "whales-uuid"));
+        site.insert(page("de", "blog/whales", "2", "post", ""));
+        site.insert(page("en", "blog/dolphins", "1", "post", ""));
+        site.insert(page("en", "blog/drafts", "", "post", ""));
+
+        let blog = site.page("en/blog", None).unwrap();
+        let children: Vec<&str> = blog.children(&site).iter().map(|m| m.id()).collect();
+        assert_eq!(
+            children,
+            vec!["blog/dolphins", "blog/whales", "blog/drafts"]
+        );
+        assert_eq!(site.pages_with_template("post").len(), 4);
+        assert_eq!(site.pages_in("de").len(), 1);
+
+        assert_eq!(site.page("whales-uuid", None).unwrap().language(), "en");
+        assert_eq!(
+            site.page("whales-uuid", Some("de")).unwrap().language(),
+            "de"
+        );
+        assert_eq!(site.page_by_uuid("blog-uuid", Some("de")), None);
+
+        // replacing and removing updates the index
+        site.insert(page("en", "blog/whales", "2", "article", "whales-uuid"));
+        assert_eq!(site.pages_with_template("post").len(), 3);
+        assert_eq!(site.pages_with_template("article").len(), 1);
+        site.remove("en/blog/dolphins");
+        let blog = site.page("en/blog", None).unwrap();
+        assert_eq!(blog.children(&site).len(), 2);
+        assert_eq!(site.pages_with_template("post").len(), 2);
     }
 }
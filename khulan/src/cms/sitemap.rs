@@ -0,0 +1,127 @@
+use crate::cms::model::Model;
+use crate::cms::site::Site;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use url::Url;
+
+/// A translation of a page for hreflang links, `x-default` for the default language
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Alternate {
+    pub hreflang: String,
+    pub href: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SitemapEntry {
+    pub loc: String,
+    pub lastmod: String,
+    pub alternates: Vec<Alternate>,
+}
+
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct Sitemap {
+    pub entries: Vec<SitemapEntry>,
+}
+
+impl Sitemap {
+    /// the pages with their urls on the host, or on the site url if there is none
+    pub fn from_site(site: &Site, host: Option<&str>) -> Self {
+        let host = site.route_host(host);
+
+        let mut entries: Vec<SitemapEntry> = site
+            .models
+            .values()
+            .filter(|model| model.is_page() && model.is_published())
+            .filter(|model| model.id().trim_matches('/') != site.error_id())
+            .filter(|model| !site.is_members_only(model))
+            .filter(|model| {
+                Url::parse(&model.url()).is_ok_and(|url| url.host_str() == Some(host.as_str()))
+            })
+            .map(|model| SitemapEntry {
+                loc: model.url(),
+                lastmod: DateTime::<Utc>::from(model.last_modified())
+                    .format("%Y-%m-%d")
+                    .to_string(),
+                alternates: model.alternates(site),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.loc.cmp(&b.loc));
+
+        Self { entries }
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\" xmlns:xhtml=\"http://www.w3.org/1999/xhtml\">\n",
+        );
+        for entry in self.entries.iter() {
+            xml.push_str("  <url>\n");
+            xml.push_str(&format!("    <loc>{}</loc>\n", escape(&entry.loc)));
+            xml.push_str(&format!("    <lastmod>{}</lastmod>\n", entry.lastmod));
+            for alternate in entry.alternates.iter() {
+                xml.push_str(&format!(
+                    "    <xhtml:link rel=\"alternate\" hreflang=\"{}\" href=\"{}\"/>\n",
+                    escape(&alternate.hreflang),
+                    escape(&alternate.href)
+                ));
+            }
+            xml.push_str("  </url>\n");
+        }
+        xml.push_str("</urlset>\n");
+        xml
+    }
+}
+
+impl Model {
+    /// the urls of all translations of the page, empty if there are none
+    pub fn alternates(&self, site: &Site) -> Vec<Alternate> {
+        let mut alternates: Vec<Alternate> = site
+            .languages
+            .iter()
+            .filter_map(|language| {
+                self.url_for(language.code()).map(|url| Alternate {
+                    hreflang: language.code().to_string(),
+                    href: url.to_string(),
+                })
+            })
+            .collect();
+        if alternates.len() < 2 {
+            return vec![];
+        }
+
+        let default = site
+            .default_language()
+            .and_then(|language| self.url_for(language.code()));
+        if let Some(url) = default {
+            alternates.push(Alternate {
+                hreflang: "x-default".to_string(),
+                href: url.to_string(),
+            });
+        }
+        alternates
+    }
+
+    /// the `<link rel="alternate">` tags of the translations for the head of the page
+    pub fn hreflang(&self, site: &Site) -> String {
+        self.alternates(site)
+            .iter()
+            .map(|alternate| {
+                format!(
+                    "<link rel=\"alternate\" hreflang=\"{}\" href=\"{}\">",
+                    escape(&alternate.hreflang),
+                    escape(&alternate.href)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,526 @@
+use crate::cms::model::Model;
+use crate::cms::site::{Resolved, Site};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, ImageResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufWriter, Cursor};
+use std::path::{Path, PathBuf};
+
+/// the quality of thumbs without a quality, like in kirby
+pub const DEFAULT_QUALITY: u8 = 90;
+
+/// the folder next to the thumbs with the options of the thumbs that are not generated yet
+const JOBS: &str = ".jobs";
+
+/// The formats thumbs are encoded in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbFormat {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Avif,
+}
+
+impl ThumbFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "jpg" | "jpeg" | "jpe" => Some(ThumbFormat::Jpeg),
+            "png" => Some(ThumbFormat::Png),
+            "gif" => Some(ThumbFormat::Gif),
+            "webp" => Some(ThumbFormat::Webp),
+            "avif" => Some(ThumbFormat::Avif),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ThumbFormat::Jpeg => "jpg",
+            ThumbFormat::Png => "png",
+            ThumbFormat::Gif => "gif",
+            ThumbFormat::Webp => "webp",
+            ThumbFormat::Avif => "avif",
+        }
+    }
+
+    /// if the quality is used, since webp is encoded lossless like png and gif
+    pub fn is_lossy(&self) -> bool {
+        matches!(self, ThumbFormat::Jpeg | ThumbFormat::Avif)
+    }
+}
+
+/// The options of a thumb like kirby's `thumb()`. Thumbs are never larger than their
+/// image and keep its format without a format.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct ThumbOptions {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// crop to the width and height around the focus of the image instead of fitting into them
+    pub crop: bool,
+    /// only for jpeg and avif, webp thumbs are lossless
+    pub quality: Option<u8>,
+    pub format: Option<ThumbFormat>,
+}
+
+impl ThumbOptions {
+    /// fit into the width and the height if there is one, like kirby's `resize()`
+    pub fn resize(width: u32, height: Option<u32>) -> Self {
+        Self {
+            width: Some(width),
+            height,
+            ..Default::default()
+        }
+    }
+
+    /// crop to the width and height, or a square without a height, like kirby's `crop()`
+    pub fn crop(width: u32, height: Option<u32>) -> Self {
+        Self {
+            width: Some(width),
+            height,
+            crop: true,
+            ..Default::default()
+        }
+    }
+
+    /// the size of the thumb of an image with the width and height
+    pub fn dimensions(&self, width: u32, height: u32) -> (u32, u32) {
+        let (width, height) = (width.max(1) as f64, height.max(1) as f64);
+        let (w, h) = match (self.width, self.height) {
+            (None, None) => (width, height),
+            (Some(w), None) | (None, Some(w)) if self.crop => (w as f64, w as f64),
+            (Some(w), Some(h)) if self.crop => (w as f64, h as f64),
+            (Some(w), None) => (w as f64, w as f64 * height / width),
+            (None, Some(h)) => (h as f64 * width / height, h as f64),
+            (Some(w), Some(h)) => {
+                let scale = (w as f64 / width).min(h as f64 / height);
+                (width * scale, height * scale)
+            }
+        };
+        // images are not upscaled like in kirby
+        let scale = (width / w).min(height / h).min(1.0);
+        (
+            ((w * scale).round() as u32).max(1),
+            ((h * scale).round() as u32).max(1),
+        )
+    }
+
+    /// the name of the thumb like kirby's `whale-300x200-crop-q80.jpg`, with the focus
+    /// for crops around a focus and without a quality for lossless formats
+    pub fn filename(&self, filename: &str, focus: Option<(f64, f64)>) -> String {
+        let (name, extension) = filename.rsplit_once('.').unwrap_or((filename, ""));
+        let mut thumb = format!(
+            "{}-{}x{}",
+            name,
+            self.width.map(|w| w.to_string()).unwrap_or_default(),
+            self.height.map(|h| h.to_string()).unwrap_or_default()
+        );
+        if self.crop {
+            thumb.push_str("-crop");
+            if let Some((x, y)) = focus {
+                thumb.push_str(&format!("-{}-{}", percent(x), percent(y)));
+            }
+        }
+        let format = self.format.or(ThumbFormat::from_extension(extension));
+        if let Some(quality) = self
+            .quality
+            .filter(|_| format.is_some_and(|f| f.is_lossy()))
+        {
+            thumb.push_str(&format!("-q{}", quality));
+        }
+        let extension = self
+            .format
+            .map(|format| format.extension())
+            .unwrap_or(extension);
+        format!("{}.{}", thumb, extension)
+    }
+}
+
+fn percent(value: f64) -> String {
+    format!("{}", (value * 1000.0).round() / 10.0)
+}
+
+/// A thumb of an image with its url, generated with its first request
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Thumb {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// the thumb that is generated with the first request for its name
+#[derive(Debug, Serialize, Deserialize)]
+struct ThumbJob {
+    source: String,
+    options: ThumbOptions,
+    focus: (f64, f64),
+}
+
+/// The focus of the image from kirby's `focus` field like `35.5% 60.2%`. Crops keep the
+/// focus as close to their center as possible, or the center without a focus.
+pub fn parse_focus(value: &str) -> Option<(f64, f64)> {
+    let mut values = value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            let percent = value.ends_with('%');
+            value
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .ok()
+                .map(|value| match percent || value > 1.0 {
+                    true => value / 100.0,
+                    false => value,
+                })
+                .map(|value| value.clamp(0.0, 1.0))
+        });
+    match (values.next(), values.next(), values.next()) {
+        (Some(Some(x)), Some(Some(y)), None) => Some((x, y)),
+        _ => None,
+    }
+}
+
+impl Model {
+    pub fn focus(&self) -> Option<(f64, f64)> {
+        self.content()
+            .fields
+            .get("focus")
+            .and_then(|focus| parse_focus(focus.value()))
+    }
+
+    /// if thumbs can be generated from the file
+    pub fn is_resizable(&self) -> bool {
+        matches!(
+            self.extension().as_deref(),
+            Some("jpg" | "jpeg" | "jpe" | "png" | "gif" | "webp" | "bmp" | "tif" | "tiff")
+        )
+    }
+
+    /// The thumb of an image, like kirby's `thumb()`. It is generated with the first request
+    /// of its url and cached in the media folder. None for files that are not resizable.
+    pub fn thumb(&self, site: &Site, options: &ThumbOptions) -> Option<Thumb> {
+        if !self.is_resizable() {
+            return None;
+        }
+        let source = self.filename()?;
+        let folder = self.media_folder()?;
+        let (width, height) = match self.dimensions() {
+            Some(dimensions) => dimensions,
+            None => image::image_dimensions(self.file_root()?).ok()?,
+        };
+        let focus = self.focus().filter(|_| options.crop);
+        let mut options = options.clone();
+        if options.format.is_none() {
+            // formats that can be read but not written become png
+            options.format = Some(
+                self.extension()
+                    .and_then(|extension| ThumbFormat::from_extension(&extension))
+                    .unwrap_or(ThumbFormat::Png),
+            );
+        }
+        let name = options.filename(&source, focus);
+
+        let dir = site.media_dir().join(&folder);
+        if !dir.join(&name).is_file() {
+            let job = ThumbJob {
+                source,
+                options: options.clone(),
+                focus: focus.unwrap_or((0.5, 0.5)),
+            };
+            fs::create_dir_all(dir.join(JOBS)).ok()?;
+            fs::write(
+                dir.join(JOBS).join(format!("{}.json", name)),
+                serde_json::to_string(&job).ok()?,
+            )
+            .ok()?;
+        }
+
+        let (width, height) = options.dimensions(width, height);
+        Some(Thumb {
+            url: format!(
+                "{}/media/{}/{}",
+                site.url().as_str().trim_end_matches('/'),
+                folder,
+                name
+            ),
+            width,
+            height,
+        })
+    }
+
+    pub fn resize(&self, site: &Site, width: u32, height: Option<u32>) -> Option<Thumb> {
+        self.thumb(site, &ThumbOptions::resize(width, height))
+    }
+
+    pub fn crop(&self, site: &Site, width: u32, height: Option<u32>) -> Option<Thumb> {
+        self.thumb(site, &ThumbOptions::crop(width, height))
+    }
+
+    /// the `srcset` of an `img` with thumbs of the widths, like `whale-300x.jpg 300w, ...`
+    pub fn srcset(&self, site: &Site, widths: &[u32], options: &ThumbOptions) -> String {
+        // widths beyond the image are clamped to it, and each width is only allowed once
+        let mut emitted: Vec<u32> = vec![];
+        widths
+            .iter()
+            .filter_map(|width| {
+                let options = ThumbOptions {
+                    width: Some(*width),
+                    height: None,
+                    crop: false,
+                    ..options.clone()
+                };
+                let thumb = self.thumb(site, &options)?;
+                if emitted.contains(&thumb.width) {
+                    return None;
+                }
+                emitted.push(thumb.width);
+                Some(format!("{} {}w", thumb.url, thumb.width))
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+impl Site {
+    /// The cached thumb of a media path like `pages/blog/whales/<hash>/whale-300x.webp`.
+    /// Only thumbs that were created with `thumb` are generated, on their first request.
+    pub fn thumb_file(&self, search: &str) -> Option<PathBuf> {
+        let (folder, name) = search.trim_matches('/').rsplit_once('/')?;
+        let dir = self.media_dir().join(folder);
+        let job = fs::read_to_string(dir.join(JOBS).join(format!("{}.json", name))).ok()?;
+        let job: ThumbJob = serde_json::from_str(&job).ok()?;
+
+        // the image is checked like its own media url, outdated thumbs are not served
+        let source = match self.resolve_media(&format!("{}/{}", folder, job.source))? {
+            Resolved::Page(source) => source,
+            Resolved::Redirect(_) => return None,
+        };
+        let target = dir.join(name);
+        if !target.is_file() {
+            generate(
+                Path::new(&source.file_root()?),
+                &target,
+                &job.options,
+                job.focus,
+            )
+            .ok()?;
+        }
+        Some(target)
+    }
+}
+
+/// Resize or crop the image into the target, written at once for concurrent requests
+pub fn generate(
+    source: &Path,
+    target: &Path,
+    options: &ThumbOptions,
+    focus: (f64, f64),
+) -> ImageResult<()> {
+    // rotated like it is displayed
+    let mut decoder = ImageReader::open(source)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    let (width, height) = options.dimensions(image.width(), image.height());
+    let image = match options.crop {
+        true => crop(&image, width, height, focus),
+        false => image.resize_exact(width, height, FilterType::Lanczos3),
+    };
+
+    let format = options
+        .format
+        .or(source
+            .extension()
+            .and_then(|extension| ThumbFormat::from_extension(&extension.to_string_lossy())))
+        .unwrap_or(ThumbFormat::Png);
+    let quality = options.quality.unwrap_or(DEFAULT_QUALITY).clamp(1, 100);
+    let mut bytes = Cursor::new(vec![]);
+    match format {
+        ThumbFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality))?,
+        // the image crate only encodes lossless webp, so there is no quality in its name
+        ThumbFormat::Webp => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut bytes))?,
+        ThumbFormat::Avif => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(AvifEncoder::new_with_speed_quality(&mut bytes, 8, quality))?,
+        ThumbFormat::Png => image.write_to(&mut bytes, ImageFormat::Png)?,
+        ThumbFormat::Gif => image.write_to(&mut bytes, ImageFormat::Gif)?,
+    }
+
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // a temp file of its own, as requests of the same thumb run at the same time
+    let dir = target.parent().unwrap_or(Path::new("."));
+    let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+    {
+        let mut file = BufWriter::new(temp.as_file_mut());
+        std::io::Write::write_all(&mut file, bytes.get_ref())?;
+    }
+    temp.persist(target).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// scale the image to cover the size and cut it around the focus
+fn crop(image: &DynamicImage, width: u32, height: u32, focus: (f64, f64)) -> DynamicImage {
+    let scale = (width as f64 / image.width() as f64).max(height as f64 / image.height() as f64);
+    let scaled_width = ((image.width() as f64 * scale).ceil() as u32).max(width);
+    let scaled_height = ((image.height() as f64 * scale).ceil() as u32).max(height);
+    let scaled = image.resize_exact(scaled_width, scaled_height, FilterType::Lanczos3);
+    let offset = |focus: f64, scaled: u32, size: u32| {
+        ((focus * scaled as f64 - size as f64 / 2.0).round() as i64)
+            .clamp(0, (scaled - size) as i64) as u32
+    };
+    scaled.crop_imm(
+        offset(focus.0, scaled_width, width),
+        offset(focus.1, scaled_height, height),
+        width,
+        height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::content::Content;
+    use crate::cms::field::Field;
+    use crate::cms::language::Languages;
+    use crate::cms::model::{ModelBuilder, ModelKind};
+    use crate::cms::site::SiteBuilder;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn it_computes_thumb_dimensions() {
+        assert_eq!(
+            ThumbOptions::resize(300, None).dimensions(1200, 800),
+            (300, 200)
+        );
+        assert_eq!(
+            ThumbOptions::resize(300, Some(100)).dimensions(1200, 800),
+            (150, 100)
+        );
+        assert_eq!(
+            ThumbOptions::crop(300, None).dimensions(1200, 800),
+            (300, 300)
+        );
+        assert_eq!(
+            ThumbOptions::crop(300, Some(100)).dimensions(1200, 800),
+            (300, 100)
+        );
+        // not upscaled
+        assert_eq!(
+            ThumbOptions::resize(2400, None).dimensions(1200, 800),
+            (1200, 800)
+        );
+        assert_eq!(
+            ThumbOptions::crop(1600, None).dimensions(1200, 800),
+            (800, 800)
+        );
+    }
+
+    #[test]
+    fn it_names_thumbs_like_kirby() {
+        let options = ThumbOptions {
+            quality: Some(80),
+            format: Some(ThumbFormat::Webp),
+            ..ThumbOptions::crop(300, Some(200))
+        };
+        assert_eq!(
+            options.filename("whale.jpg", None),
+            "whale-300x200-crop.webp"
+        );
+        assert_eq!(
+            options.filename("whale.jpg", Some((0.355, 0.6))),
+            "whale-300x200-crop-35.5-60.webp"
+        );
+        let options = ThumbOptions {
+            format: None,
+            ..options
+        };
+        assert_eq!(
+            options.filename("whale.jpg", None),
+            "whale-300x200-crop-q80.jpg"
+        );
+        assert_eq!(
+            options.filename("whale.png", None),
+            "whale-300x200-crop.png"
+        );
+        assert_eq!(
+            ThumbOptions::resize(300, None).filename("whale.jpg", None),
+            "whale-300x.jpg"
+        );
+        assert_eq!(parse_focus("35.5% 60.2%"), Some((0.355, 0.602)));
+        assert_eq!(parse_focus("0.2, 0.8"), Some((0.2, 0.8)));
+        assert_eq!(parse_focus(""), None);
+    }
+
+    #[test]
+    fn it_generates_thumbs_on_request() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path().join("storage/content/blog/1_whales");
+        fs::create_dir_all(&folder).unwrap();
+        // the left half is red and the right half blue
+        let image = RgbImage::from_fn(40, 20, |x, _| match x < 20 {
+            true => Rgb([255, 0, 0]),
+            false => Rgb([0, 0, 255]),
+        });
+        image.save(folder.join("whale.png")).unwrap();
+
+        let mut site = SiteBuilder::new()
+            .dir(&temp_dir.path().to_path_buf())
+            .build();
+        site.languages = Languages::from_codes(&["en"]);
+        site.insert(
+            ModelBuilder::new()
+                .kind(&ModelKind::File)
+                .path("blog/whales")
+                .language("en")
+                .root(&folder.join("whale.png.en.txt").to_string_lossy())
+                .content(&Content::new(Some(
+                    &[("focus".to_string(), Field::new("focus", Some("90% 50%")))]
+                        .into_iter()
+                        .collect(),
+                )))
+                .build(),
+        );
+        let whale = site.file("blog/whales/whale.png", None).unwrap();
+
+        let thumb = whale.resize(&site, 20, None).unwrap();
+        assert_eq!((thumb.width, thumb.height), (20, 10));
+        let path = thumb.url.trim_start_matches("http://localhost:8000/media/");
+        assert!(path.ends_with("/whale-20x.png"));
+        let file = site.thumb_file(path).unwrap();
+        assert_eq!(image::image_dimensions(&file).unwrap(), (20, 10));
+
+        // the crop is cut around the focus on the right
+        let thumb = whale.crop(&site, 10, None).unwrap();
+        assert!(thumb.url.ends_with("/whale-10x-crop-90-50.png"));
+        let path = thumb.url.trim_start_matches("http://localhost:8000/media/");
+        let crop = image::open(site.thumb_file(path).unwrap()).unwrap();
+        assert_eq!(crop.to_rgb8().get_pixel(5, 5), &Rgb([0, 0, 255]));
+
+        let srcset = whale.srcset(
+            &site,
+            &[10, 20, 80],
+            &ThumbOptions {
+                format: Some(ThumbFormat::Webp),
+                ..Default::default()
+            },
+        );
+        assert!(srcset.contains("/whale-10x.webp 10w, "));
+        assert!(srcset.ends_with("/whale-80x.webp 40w"));
+        let srcset = whale.srcset(&site, &[40, 80], &ThumbOptions::default());
+        assert_eq!(srcset.matches(" 40w").count(), 1);
+
+        // only thumbs created by templates are generated
+        let folder = path.rsplit_once('/').unwrap().0;
+        assert_eq!(site.thumb_file(&format!("{}/whale-5x.png", folder)), None);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::cms::model::Model;
+use crate::cms::site::Site;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// fields that are never translated, like the `uuid` kirby stores in the default language only
+const IGNORED_FIELDS: [&str; 2] = ["uuid", "slug"];
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TranslationStatus {
+    /// the logical page, like `blog/whales`
+    pub page: String,
+    pub language: String,
+    /// the content file of the translation relative to the content folder, if there is one
+    pub file: Option<String>,
+    pub missing: bool,
+    /// fields absent in the translation or identical to the default language
+    pub untranslated: Vec<String>,
+    /// if the default language was changed after the translation
+    pub outdated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct TranslationReport {
+    pub complete: bool,
+    pub checked: usize,
+    /// the pages and languages with missing, untranslated or outdated content
+    pub translations: Vec<TranslationStatus>,
+}
+
+impl TranslationReport {
+    pub fn from_site(site: &Site) -> Self {
+        let mut report = Self::default();
+        let default = match site.default_language() {
+            Some(language) => language.code(),
+            None => {
+                report.complete = true;
+                return report;
+            }
+        };
+
+        let sources = site
+            .models
+            .values()
+            .filter(|model| (model.is_page() || model.is_site()) && model.language() == default)
+            // drafts and pages of members are not listed for everyone with access to the report
+            .filter(|model| model.is_published() && !site.is_members_only(model));
+        for source in sources {
+            for language in site.languages.iter().filter(|l| l.code() != default) {
+                report.checked += 1;
+                let status = Self::status(site, source, language.code());
+                if status.missing || status.outdated || !status.untranslated.is_empty() {
+                    report.translations.push(status);
+                }
+            }
+        }
+
+        report
+            .translations
+            .sort_by(|a, b| (&a.page, &a.language).cmp(&(&b.page, &b.language)));
+        report.complete = report.translations.is_empty();
+        report
+    }
+
+    /// compare the translation of the page in the language with its source in the default language
+    pub fn status(site: &Site, source: &Model, language: &str) -> TranslationStatus {
+        let mut status = TranslationStatus {
+            page: source.identity(),
+            language: language.to_string(),
+            file: None,
+            missing: true,
+            untranslated: vec![],
+            outdated: false,
+        };
+        let translation = match source.translation(site, language) {
+            Some(translation) => translation,
+            None => return status,
+        };
+
+        let untranslatable: Vec<&str> = source
+            .blueprint(site)
+            .map(|blueprint| {
+                blueprint
+                    .fields
+                    .values()
+                    .filter(|field| !field.translate)
+                    .map(|field| field.name.as_str())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let root = PathBuf::from(translation.root());
+        let file = root.strip_prefix(site.content_dir()).unwrap_or(&root);
+        status.file = Some(file.to_string_lossy().to_string());
+        status.missing = false;
+        status.outdated = translation.last_modified() < source.last_modified();
+        status.untranslated = source
+            .content()
+            .fields
+            .iter()
+            .filter(|(name, field)| {
+                !IGNORED_FIELDS.contains(&name.as_str())
+                    && !untranslatable.contains(&name.as_str())
+                    && !field.value().trim().is_empty()
+            })
+            .filter(|(name, field)| {
+                !translation.is_translated(name)
+                    || translation
+                        .content()
+                        .fields
+                        .get(*name)
+                        .is_some_and(|translated| translated.value() == field.value())
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        status.untranslated.sort();
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::content::Content;
+    use crate::cms::field::Field;
+    use crate::cms::language::{Language, Languages};
+    use crate::cms::model::{ModelBuilder, ModelKind};
+    use crate::cms::site::SiteBuilder;
+    use std::ops::Sub;
+    use std::time::{Duration, SystemTime};
+
+    fn page(lang: &str, path: &str, fields: &[(&str, &str)], modified: SystemTime) -> Model {
+        ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .language(lang)
+            .path(path)
+            .root(&format!("/storage/content/{}/page.{}.txt", path, lang))
+            .last_modified(&modified)
+            .content(&Content::new(Some(
+                &fields
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), Field::new(name, Some(value))))
+                    .collect(),
+            )))
+            .build()
+    }
+
+    #[test]
+    fn it_reports_translations() {
+        let now = SystemTime::now();
+        let before = now.sub(Duration::from_secs(60));
+
+        let mut site = Site::new(None, None, None);
+        let mut en = Language::new("en");
+        en.default = true;
+        site.languages = Languages::new(vec![en, Language::new("de")]);
+        for model in [
+            page("en", "blog", &[("title", "Blog"), ("uuid", "abc")], now),
+            page("de", "blog", &[("title", "Blog")], now),
+            page("en", "about", &[("title", "About"), ("text", "Hi")], now),
+            page("de", "about", &[("title", "Über uns")], before),
+            page("en", "contact", &[("title", "Contact")], now),
+        ] {
+            site.models.insert(model.path(), model);
+        }
+        site.merge_translations();
+
+        let report = site.translation_report();
+        assert!(!report.complete);
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.translations.len(), 3);
+
+        let about = &report.translations[0];
+        assert_eq!(about.page, "about");
+        assert_eq!(about.file.as_deref(), Some("about/page.de.txt"));
+        assert_eq!(about.untranslated, vec!["text"]);
+        assert!(about.outdated);
+
+        let blog = &report.translations[1];
+        assert_eq!(blog.untranslated, vec!["title"]);
+        assert!(!blog.outdated);
+
+        let contact = &report.translations[2];
+        assert_eq!(contact.page, "contact");
+        assert!(contact.missing);
+        assert_eq!(contact.file, None);
+    }
+
+    #[test]
+    fn it_skips_drafts_and_pages_of_members() {
+        let now = SystemTime::now();
+        let mut site = SiteBuilder::new().members_only(&["members"]).build();
+        let mut en = Language::new("en");
+        en.default = true;
+        site.languages = Languages::new(vec![en, Language::new("de")]);
+        let draft = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .language("en")
+            .path("draft")
+            .root("/storage/content/_drafts/draft/page.en.txt")
+            .build();
+        for model in [
+            page("en", "blog", &[("title", "Blog")], now),
+            page("en", "members", &[("title", "Members")], now),
+            page("en", "members/area", &[("title", "Area")], now),
+            draft,
+        ] {
+            site.models.insert(model.path(), model);
+        }
+        site.merge_translations();
+
+        let report = site.translation_report();
+        assert_eq!(report.checked, 1);
+        assert_eq!(report.translations.len(), 1);
+        assert_eq!(report.translations[0].page, "blog");
+    }
+}
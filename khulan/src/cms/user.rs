@@ -0,0 +1,268 @@
+use crate::cms::field::Field;
+use crate::cms::model::Model;
+use crate::cms::pages::Pages;
+use crate::cms::site::{Resolved, Site};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// the accounts in `site/accounts`, sorted by id unless sorted otherwise
+pub type Users<'a> = Pages<'a>;
+
+/// the scheme of the uuids of users in fields, like `user://a1b2c3d4`
+pub const USER_SCHEME: &str = "user://";
+
+/// the names of the avatars of users in their account folder, like kirby's `profile.jpg`
+const AVATARS: [&str; 5] = [
+    "profile.jpg",
+    "profile.jpeg",
+    "profile.png",
+    "profile.gif",
+    "profile.webp",
+];
+
+impl Model {
+    pub fn email(&self) -> &str {
+        match self.content().fields.get("email") {
+            Some(email) => email.value(),
+            None => "",
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self.content().fields.get("name") {
+            Some(name) => name.value(),
+            None => "",
+        }
+    }
+
+    /// the name or else the email, like kirby's `nameOrEmail()`
+    pub fn name_or_email(&self) -> &str {
+        match self.name().trim() {
+            "" => self.email(),
+            name => name,
+        }
+    }
+
+    /// the role of users, which is also the template of their blueprint like `users/editor`
+    pub fn role(&self) -> Option<&str> {
+        self.is_user().then(|| self.template())
+    }
+
+    /// the folder of the account like `site/accounts/a1b2c3d4`
+    pub fn account_dir(&self) -> Option<PathBuf> {
+        if !self.is_user() {
+            return None;
+        }
+        PathBuf::from(self.root()).parent().map(Path::to_path_buf)
+    }
+
+    /// the image of the user like `profile.jpg` in the folder of the account
+    pub fn avatar(&self) -> Option<PathBuf> {
+        let dir = self.account_dir()?;
+        AVATARS
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|avatar| avatar.is_file())
+    }
+
+    /// the bcrypt hash of the password in `.htpasswd` of the account, which is shared
+    /// with the panel
+    fn password_hash(&self) -> Option<String> {
+        let hash = fs::read_to_string(self.account_dir()?.join(".htpasswd")).ok()?;
+        Some(hash.trim().to_string()).filter(|hash| !hash.is_empty())
+    }
+
+    /// if the password matches the hash of the account, never for accounts without password
+    pub fn verify_password(&self, password: &str) -> bool {
+        self.password_hash()
+            .is_some_and(|hash| bcrypt::verify(password, &hash).unwrap_or(false))
+    }
+
+    /// changes with the password, so sessions end when the password is changed
+    pub fn password_fingerprint(&self) -> Option<String> {
+        self.password_hash()
+            .map(|hash| format!("{:08x}", crc32fast::hash(hash.as_bytes())))
+    }
+
+    /// changes with the avatar like the hash of files
+    pub fn avatar_hash(&self) -> Option<String> {
+        let avatar = self.avatar()?;
+        let modified = fs::metadata(&avatar)
+            .and_then(|metadata| metadata.modified())
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs();
+        Some(format!(
+            "{:08x}-{}",
+            crc32fast::hash(avatar.to_string_lossy().as_bytes()),
+            modified
+        ))
+    }
+
+    /// the url of the avatar like `/media/users/a1b2c3d4/<hash>/profile.jpg` on the site url
+    pub fn avatar_url(&self, site: &Site) -> Option<String> {
+        let avatar = self.avatar()?;
+        Some(format!(
+            "{}/media/users/{}/{}/{}",
+            site.url().as_str().trim_end_matches('/'),
+            self.id(),
+            self.avatar_hash()?,
+            avatar.file_name()?.to_string_lossy()
+        ))
+    }
+}
+
+impl Site {
+    /// the users in the language, or in the default language if none is given
+    pub fn users(&self, lang: Option<&str>) -> Users<'_> {
+        let lang = lang
+            .or(self.default_language().map(|language| language.code()))
+            .unwrap_or("");
+        let mut users: Vec<&Model> = self
+            .index
+            .users(lang)
+            .filter_map(|key| self.models.get(key))
+            .collect();
+        users.sort_by(|a, b| a.id().cmp(b.id()));
+        Users::new(users)
+    }
+
+    /// the user by id, email or uuid like `user://a1b2c3d4`, in the language or else the
+    /// default language
+    pub fn user(&self, search: &str, lang: Option<&str>) -> Option<&Model> {
+        let search = search.trim();
+        let search = search.strip_prefix(USER_SCHEME).unwrap_or(search);
+        let id = match search.contains('@') {
+            true => self.index.email(search)?.as_str(),
+            false => search,
+        };
+        self.users(lang).iter().find(|user| user.id() == id)
+    }
+
+    /// Resolve an avatar path like `users/a1b2c3d4/<hash>/profile.jpg` to its user.
+    /// Old urls redirect to the current one like for files.
+    pub fn resolve_avatar(&self, search: &str) -> Option<Resolved<'_>> {
+        let segments: Vec<&str> = search.trim_matches('/').split('/').collect();
+        let (id, hash, filename) = match segments.as_slice() {
+            ["users", id, hash, filename] => (*id, *hash, *filename),
+            _ => return None,
+        };
+        let user = self.user(id, None).filter(|user| user.id() == id)?;
+        let avatar = user.avatar()?;
+        if avatar.file_name()?.to_string_lossy() != filename {
+            return None;
+        }
+        match user.avatar_hash().as_deref() == Some(hash) {
+            true => Some(Resolved::Page(user)),
+            false => Some(Resolved::Redirect(user)),
+        }
+    }
+}
+
+impl Field {
+    /// The users of a `users` field, which are stored as a yaml list of uuids like
+    /// `- user://a1b2c3d4` or as emails or ids separated by commas in older sites.
+    pub fn to_users<'a>(&self, site: &'a Site, lang: Option<&str>) -> Users<'a> {
+        self.value()
+            .lines()
+            .flat_map(|line| line.split(','))
+            .map(|item| item.trim().trim_start_matches('-').trim())
+            .filter(|item| !item.is_empty())
+            .filter_map(|item| site.user(item, lang))
+            .collect()
+    }
+
+    /// the first user of a `users` field, like kirby's `toUser()`
+    pub fn to_user<'a>(&self, site: &'a Site, lang: Option<&str>) -> Option<&'a Model> {
+        self.to_users(site, lang).first()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::content::Content;
+    use crate::cms::model::{ModelBuilder, ModelKind};
+    use crate::cms::site::SiteBuilder;
+
+    fn user(id: &str, email: &str, name: &str) -> Model {
+        ModelBuilder::new()
+            .kind(&ModelKind::User)
+            .path(id)
+            .template("editor")
+            .content(&Content::new(Some(
+                &[
+                    ("email".to_string(), Field::new("email", Some(email))),
+                    ("name".to_string(), Field::new("name", Some(name))),
+                ]
+                .into_iter()
+                .collect(),
+            )))
+            .build()
+    }
+
+    #[test]
+    fn it_finds_users() {
+        let mut site = SiteBuilder::new().build();
+        site.insert(user("a1b2c3d4", "bastian@example.com", "Bastian"));
+        site.insert(user("e5f6g7h8", "nico@example.com", ""));
+        // a page with the id of a user
+        site.insert(
+            ModelBuilder::new()
+                .kind(&ModelKind::Page)
+                .path("a1b2c3d4")
+                .build(),
+        );
+
+        assert_eq!(site.users(None).len(), 2);
+        let bastian = site.user("a1b2c3d4", None).unwrap();
+        assert_eq!(bastian.name(), "Bastian");
+        assert_eq!(bastian.role(), Some("editor"));
+        assert_eq!(site.user("Bastian@Example.com", None), Some(bastian));
+        assert_eq!(site.user("user://a1b2c3d4", None), Some(bastian));
+        assert_eq!(site.user("nobody@example.com", None), None);
+        assert_eq!(
+            site.user("e5f6g7h8", None).unwrap().name_or_email(),
+            "nico@example.com"
+        );
+        assert!(site.page("a1b2c3d4", None).unwrap().is_page());
+
+        let authors = Field::new("authors", Some("- user://e5f6g7h8\n- user://a1b2c3d4"));
+        let ids: Vec<&str> = authors
+            .to_users(&site, None)
+            .iter()
+            .map(|user| user.id())
+            .collect();
+        assert_eq!(ids, vec!["e5f6g7h8", "a1b2c3d4"]);
+        let author = Field::new("author", Some("bastian@example.com, unknown"));
+        assert_eq!(author.to_user(&site, None), Some(bastian));
+        assert_eq!(author.to_users(&site, None).len(), 1);
+    }
+
+    #[test]
+    fn it_verifies_passwords() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let account = temp_dir.path().join("a1b2c3d4");
+        std::fs::create_dir_all(&account).unwrap();
+        std::fs::write(account.join("index.php"), "").unwrap();
+        let bastian = ModelBuilder::new()
+            .kind(&ModelKind::User)
+            .path("a1b2c3d4")
+            .content(user("a1b2c3d4", "bastian@example.com", "Bastian").content())
+            .root(&account.join("index.php").to_string_lossy())
+            .build();
+        assert!(!bastian.verify_password(""));
+        assert_eq!(bastian.password_fingerprint(), None);
+
+        // like the hashes of the panel, which are `$2y$` hashes of php
+        let hash = bcrypt::hash("secret", 4)
+            .unwrap()
+            .replacen("$2b$", "$2y$", 1);
+        std::fs::write(account.join(".htpasswd"), &hash).unwrap();
+        assert!(bastian.verify_password("secret"));
+        assert!(!bastian.verify_password("Secret"));
+        assert!(bastian.password_fingerprint().is_some());
+    }
+}
@@ -0,0 +1,367 @@
+use crate::cms::blueprint::{Blueprint, FieldBlueprint, FieldKind};
+use crate::cms::field::Field;
+use crate::cms::model::Model;
+use crate::cms::site::Site;
+use serde::Serialize;
+use url::Url;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ValidationError {
+    pub file: String,
+    pub path: String,
+    pub template: String,
+    pub field: String,
+    pub rule: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub checked: usize,
+    pub errors: Vec<ValidationError>,
+}
+
+impl ValidationReport {
+    pub fn from_site(site: &Site) -> Self {
+        let mut report = Self::default();
+
+        for model in site.models.values() {
+            if let Some(blueprint) = model.blueprint(site) {
+                report.checked += 1;
+                report.errors.extend(Self::validate_model(model, blueprint));
+            }
+        }
+
+        report
+            .errors
+            .sort_by(|a, b| (&a.file, &a.field).cmp(&(&b.file, &b.field)));
+        report.valid = report.errors.is_empty();
+        report
+    }
+
+    pub fn validate_model(model: &Model, blueprint: &Blueprint) -> Vec<ValidationError> {
+        blueprint
+            .fields
+            .values()
+            .flat_map(|declared| {
+                declared
+                    .check(model.content().fields.get(&declared.name))
+                    .into_iter()
+                    .map(|(rule, message)| ValidationError {
+                        file: model.root(),
+                        path: model.path(),
+                        template: model.template().to_string(),
+                        field: declared.name.clone(),
+                        rule: rule.to_string(),
+                        message,
+                    })
+            })
+            .collect()
+    }
+}
+
+impl FieldBlueprint {
+    /// Check a field of a model against the declared rules and
+    /// return the broken rules with a message for each.
+    pub fn check(&self, field: Option<&Field>) -> Vec<(&'static str, String)> {
+        let value = field.map(|f| f.value().trim()).unwrap_or("");
+        let mut errors = vec![];
+
+        if value.is_empty() {
+            if self.required {
+                errors.push(("required", "The field is required".to_string()));
+            }
+            return errors;
+        }
+
+        let items = Self::items(&self.kind, value);
+
+        match self.kind {
+            FieldKind::Number | FieldKind::Range => match value.parse::<f64>() {
+                Ok(number) => {
+                    if let Some(min) = self.min.filter(|min| number < *min) {
+                        errors.push(("min", format!("The number must be at least {}", min)));
+                    }
+                    if let Some(max) = self.max.filter(|max| number > *max) {
+                        errors.push(("max", format!("The number must be at most {}", max)));
+                    }
+                }
+                Err(_) => errors.push(("number", format!("'{}' is not a number", value))),
+            },
+            _ if self.kind.is_structured() || Self::is_list(&self.kind) => {
+                if let Some(min) = self.min.filter(|min| (items.len() as f64) < *min) {
+                    errors.push(("min", format!("Add at least {} entries", min)));
+                }
+                if let Some(max) = self.max.filter(|max| (items.len() as f64) > *max) {
+                    errors.push(("max", format!("Add at most {} entries", max)));
+                }
+            }
+            _ => {}
+        }
+
+        let length = value.chars().count();
+        if let Some(min) = self.min_length.filter(|min| length < *min) {
+            errors.push((
+                "minlength",
+                format!("The text must have at least {} characters", min),
+            ));
+        }
+        if let Some(max) = self.max_length.filter(|max| length > *max) {
+            errors.push((
+                "maxlength",
+                format!("The text must have at most {} characters", max),
+            ));
+        }
+
+        if !self.options.is_empty()
+            && matches!(
+                self.kind,
+                FieldKind::Select
+                    | FieldKind::Radio
+                    | FieldKind::Toggles
+                    | FieldKind::Multiselect
+                    | FieldKind::Checkboxes
+                    | FieldKind::Tags
+            )
+        {
+            for item in items.iter().filter(|item| !self.options.contains(item)) {
+                errors.push(("options", format!("'{}' is not a valid option", item)));
+            }
+        }
+
+        let implied = match self.kind {
+            FieldKind::Date => Some("date"),
+            FieldKind::Time => Some("time"),
+            FieldKind::Email => Some("email"),
+            FieldKind::Url => Some("url"),
+            _ => None,
+        };
+        // each rule once, also if the blueprint repeats the rule of the field type
+        let mut rules: Vec<&str> = vec![];
+        for rule in self.validate.iter().map(|r| r.as_str()).chain(implied) {
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+
+        for rule in rules {
+            let broken = match rule {
+                "date" if !is_date(value) => Some("date"),
+                "time" if !is_time(value) => Some("time"),
+                "email" if !is_email(value) => Some("email"),
+                "url" if !is_url(value) => Some("url"),
+                "integer" if value.parse::<i64>().is_err() => Some("integer"),
+                "num" | "number" if value.parse::<f64>().is_err() => Some("number"),
+                "alpha" if !value.chars().all(char::is_alphabetic) => Some("alpha"),
+                "alphanum" if !value.chars().all(char::is_alphanumeric) => Some("alphanum"),
+                _ => None, // unknown rules are left to the panel
+            };
+            if let Some(rule) = broken {
+                errors.push((rule, format!("'{}' is not a valid {}", value, rule)));
+            }
+        }
+
+        errors
+    }
+
+    fn is_list(kind: &FieldKind) -> bool {
+        matches!(
+            kind,
+            FieldKind::Tags | FieldKind::Multiselect | FieldKind::Checkboxes
+        )
+    }
+
+    /// split a value into its entries, either from a comma list or a yaml list
+    fn items(kind: &FieldKind, value: &str) -> Vec<String> {
+        if kind.is_structured() {
+            value
+                .lines()
+                .filter(|line| line.starts_with('-'))
+                .map(|line| line.trim_start_matches('-').trim().to_string())
+                .collect()
+        } else if Self::is_list(kind) {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        } else {
+            vec![value.to_string()]
+        }
+    }
+}
+
+/// kirby stores dates as `YYYY-MM-DD` with an optional time
+pub fn is_date(value: &str) -> bool {
+    let (date, time) = match value.split_once(' ') {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 || parts[1].len() != 2 || parts[2].len() != 2 {
+        return false;
+    }
+    let (year, month, day) = match (
+        parts[0].parse::<u32>(),
+        parts[1].parse::<u32>(),
+        parts[2].parse::<u32>(),
+    ) {
+        (Ok(year), Ok(month), Ok(day)) => (year, month, day),
+        _ => return false,
+    };
+
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+
+    day >= 1 && day <= days && time.is_none_or(is_time)
+}
+
+/// `HH:MM` or `HH:MM:SS`
+pub fn is_time(value: &str) -> bool {
+    let parts: Vec<&str> = value.trim().split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 || parts.iter().any(|p| p.len() != 2) {
+        return false;
+    }
+    let limits = [23, 59, 59];
+    parts
+        .iter()
+        .zip(limits.iter())
+        .all(|(part, limit)| part.parse::<u32>().is_ok_and(|n| n <= *limit))
+}
+
+pub fn is_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && !value.chars().any(char::is_whitespace)
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+        }
+        None => false,
+    }
+}
+
+pub fn is_url(value: &str) -> bool {
+    match Url::parse(value) {
+        Ok(url) => url.has_host() || url.scheme() == "mailto" || url.scheme() == "tel",
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::content::Content;
+    use crate::cms::model::{ModelBuilder, ModelKind};
+    use maplit::hashmap;
+    use std::collections::BTreeMap;
+
+    fn declared(name: &str, kind: FieldKind) -> FieldBlueprint {
+        FieldBlueprint {
+            name: name.to_string(),
+            kind,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_checks_values() {
+        assert!(is_date("2024-10-17"));
+        assert!(is_date("2024-02-29 12:30"));
+        assert!(!is_date("2023-02-29"));
+        assert!(!is_date("17.10.2024"));
+        assert!(is_time("23:59:59"));
+        assert!(!is_time("24:00"));
+        assert!(is_email("hello@getkhulan.com"));
+        assert!(!is_email("hello@localhost"));
+        assert!(is_url("https://getkhulan.com/blog"));
+        assert!(!is_url("getkhulan.com"));
+    }
+
+    #[test]
+    fn it_checks_fields() {
+        let mut title = declared("title", FieldKind::Text);
+        title.required = true;
+        title.max_length = Some(5);
+        assert_eq!(title.check(None)[0].0, "required");
+        assert_eq!(title.check(Some(&Field::new("title", Some("Hi")))).len(), 0);
+        assert_eq!(
+            title.check(Some(&Field::new("title", Some("Hello, World!"))))[0].0,
+            "maxlength"
+        );
+
+        let mut rating = declared("rating", FieldKind::Range);
+        rating.min = Some(1.0);
+        rating.max = Some(5.0);
+        assert_eq!(
+            rating.check(Some(&Field::new("rating", Some("6"))))[0].0,
+            "max"
+        );
+        assert_eq!(
+            rating.check(Some(&Field::new("rating", Some("x"))))[0].0,
+            "number"
+        );
+
+        let mut tags = declared("tags", FieldKind::Tags);
+        tags.options = vec!["rust".to_string(), "kirby".to_string()];
+        tags.max = Some(2.0);
+        let errors = tags.check(Some(&Field::new("tags", Some("rust, php, kirby"))));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].0, "max");
+        assert_eq!(errors[1].0, "options");
+
+        // the rule of the field type is not repeated by the blueprint
+        let mut day = declared("day", FieldKind::Date);
+        day.validate = vec!["date".to_string(), "min".to_string()];
+        assert_eq!(day.check(Some(&Field::new("day", Some("soon")))).len(), 1);
+    }
+
+    #[test]
+    fn it_validates_the_site() {
+        let mut blueprint = Blueprint {
+            name: "pages/post".to_string(),
+            title: "Post".to_string(),
+            fields: BTreeMap::new(),
+            ..Default::default()
+        };
+        let mut date = declared("date", FieldKind::Date);
+        date.required = true;
+        blueprint.fields.insert("date".to_string(), date);
+        blueprint
+            .fields
+            .insert("email".to_string(), declared("email", FieldKind::Email));
+
+        let model = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .path("blog/whales")
+            .template("post")
+            .root("/content/blog/1_whales/post.txt")
+            .content(&Content::new(Some(&hashmap! {
+                "email".to_string() => Field::new("email", Some("not-an-email"))
+            })))
+            .build();
+
+        let mut site = Site::new(None, None, None);
+        site.blueprints.insert("pages/post".to_string(), blueprint);
+        site.models.insert(model.path(), model);
+
+        let report = site.validate();
+        assert!(!report.valid);
+        assert_eq!(report.checked, 1);
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.errors[0].field, "date");
+        assert_eq!(report.errors[0].rule, "required");
+        assert_eq!(report.errors[1].field, "email");
+        assert_eq!(report.errors[1].file, "/content/blog/1_whales/post.txt");
+    }
+}
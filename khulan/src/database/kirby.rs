@@ -1,16 +1,21 @@
+use crate::cms::blueprint::Blueprint;
 use crate::cms::content::Content;
 use crate::cms::field::Field;
-use crate::cms::model::{Model, ModelBuilder, ModelKind};
+use crate::cms::language::{Direction, Language, Languages, Variable};
+use crate::cms::model::{Model, ModelBuilder, ModelKind, DRAFTS};
 use crate::cms::site::Site;
+use crate::database::php::PhpValue;
 use crate::database::{Database, DatabaseError};
 use crate::watcher::file::FileWatcher;
 use dotenvy;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::fs;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
+use walkdir::WalkDir;
+use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
 
 #[derive(Debug)]
 pub struct Kirby {}
@@ -25,11 +30,36 @@ impl Kirby {
         let model = Self::model_from_string(root_path, file_path, text);
 
         match model {
-            Some(model) => {
+            Some(mut model) => {
+                let file = file_path.to_string_lossy().to_string();
+                if !site.languages.is_empty()
+                    && !model.language().is_empty()
+                    && !site.languages.contains(model.language())
+                {
+                    println!(
+                        "Unknown language '{}' of file at path '{}'",
+                        model.language(),
+                        file
+                    );
+                    site.unknown_languages
+                        .insert(file, model.language().to_string());
+                    return;
+                }
+                site.unknown_languages.remove(&file);
+                let home = model.id().trim_matches('/') == site.home_id();
+                model.set_home(home);
+
+                if let Some(blueprint) = model.blueprint(site) {
+                    let blueprint = blueprint.clone();
+                    model.apply_blueprint(&blueprint);
+                }
+                if let Some(file) = model.file_root() {
+                    model.set_image_meta(site.image_meta(&file));
+                }
                 if model.last_modified() > site.last_modified {
                     site.last_modified = model.last_modified();
                 }
-                site.models.insert(model.path(), model);
+                site.insert(model);
             }
             None => {
                 println!(
@@ -40,12 +70,167 @@ impl Kirby {
         }
     }
 
+    /// Add a media file like `whale.jpg` as file model in every language without a
+    /// metadata file like `whale.jpg.en.txt`, which is loaded as model on its own.
+    pub fn add_file_to_site(site: &mut Site, root_path: &Path, file_path: &Path) {
+        let rel_path = match file_path.strip_prefix(root_path) {
+            Ok(rel_path) => rel_path.to_path_buf(),
+            Err(_) => return,
+        };
+        let (dir_path, _, _, _, filename) = Self::extract_components(&rel_path);
+        let last_modified = match file_path
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+        {
+            Ok(last_modified) => last_modified,
+            Err(_) => return,
+        };
+
+        let image = site.image_meta(&file_path.to_string_lossy());
+        for lang in Self::language_codes(site) {
+            let meta = match lang.is_empty() {
+                true => format!("{}.txt", filename),
+                false => format!("{}.{}.txt", filename, lang),
+            };
+            if file_path.with_file_name(meta).is_file() {
+                continue;
+            }
+            let mut model = ModelBuilder::new()
+                .kind(&ModelKind::File)
+                .language(&lang)
+                .path(&dir_path)
+                .last_modified(&last_modified)
+                .root(&file_path.to_string_lossy())
+                .build();
+            model.set_image_meta(image.clone());
+            if model.last_modified() > site.last_modified {
+                site.last_modified = model.last_modified();
+            }
+            site.insert(model);
+        }
+    }
+
+    /// the codes of the languages of the site, `""` for sites with a single language
+    fn language_codes(site: &Site) -> Vec<String> {
+        match site.languages.is_empty() {
+            true => vec!["".to_string()],
+            false => site
+                .languages
+                .codes()
+                .into_iter()
+                .map(|code| code.to_string())
+                .collect(),
+        }
+    }
+
+    /// Load the accounts like `site/accounts/<id>/index.php` with the email, name, role
+    /// and language of the user as user models, with the content of `user.txt` or
+    /// `user.<lang>.txt` in every language.
+    pub fn load_users(site: &mut Site) -> Result<(), DatabaseError> {
+        let users: Vec<String> = site
+            .models
+            .iter()
+            .filter(|(_, model)| model.is_user())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in users {
+            site.remove(&key);
+        }
+
+        let accounts_path = Self::accounts_folder_path(site);
+        site.accounts = Self::folder_state(&accounts_path);
+        if !accounts_path.is_dir() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(&accounts_path).map_err(DatabaseError::from)? {
+            let dir = entry.map_err(DatabaseError::from)?.path();
+            let id = match dir
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+            {
+                Some(id) if dir.is_dir() && !id.starts_with('.') => id,
+                _ => continue,
+            };
+            let index = dir.join("index.php");
+            let account = match fs::read_to_string(&index)
+                .ok()
+                .and_then(|text| PhpValue::parse_return(&text))
+            {
+                Some(account) => account,
+                None => {
+                    println!(
+                        "Failed to load account at path '{}'",
+                        index.to_string_lossy()
+                    );
+                    continue;
+                }
+            };
+
+            for lang in Self::language_codes(site) {
+                let file = match lang.is_empty() {
+                    true => dir.join("user.txt"),
+                    false => dir.join(format!("user.{}.txt", lang)),
+                };
+                let (mut content, root) = match fs::read_to_string(&file) {
+                    Ok(text) => (Self::content_from_string(&text), file),
+                    Err(_) => (Content::new(None), index.clone()),
+                };
+                for name in ["email", "name", "language"] {
+                    if let Some(value) = account.get(name).and_then(|value| value.as_string()) {
+                        content
+                            .fields
+                            .insert(name.to_string(), Field::new(name, Some(&value)));
+                    }
+                }
+                let last_modified = root
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .map_err(DatabaseError::from)?;
+                let mut model = ModelBuilder::new()
+                    .kind(&ModelKind::User)
+                    .language(&lang)
+                    .path(&id)
+                    // users without a role are `nobody` like in kirby
+                    .template(
+                        account
+                            .get("role")
+                            .and_then(|role| role.as_str())
+                            .unwrap_or("nobody"),
+                    )
+                    .content(&content)
+                    .last_modified(&last_modified)
+                    .root(&root.to_string_lossy())
+                    .build();
+                if let Some(blueprint) = model.blueprint(site) {
+                    let blueprint = blueprint.clone();
+                    model.apply_blueprint(&blueprint);
+                }
+                site.insert(model);
+            }
+        }
+        Ok(())
+    }
+
+    /// the latest modification and the number of the files and folders of a folder like the
+    /// accounts, which changes with every account, password or avatar saved by the panel
+    fn folder_state(path: &Path) -> Option<(SystemTime, usize)> {
+        let modified: Vec<SystemTime> = WalkDir::new(path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+            .collect();
+        Some((*modified.iter().max()?, modified.len()))
+    }
+
     pub fn model_from_string(
         root_path: &PathBuf,
         file_path: &PathBuf,
         text: &str,
     ) -> Option<Model> {
-        let content = Self::content_from_string(text);
+        let content = match file_path.extension().and_then(|ext| ext.to_str()) {
+            Some("md") => Self::content_from_markdown(text),
+            _ => Self::content_from_string(text),
+        };
         let rel_path = file_path
             .strip_prefix(root_path.clone())
             .unwrap()
@@ -106,22 +291,130 @@ impl Kirby {
         content
     }
 
+    /// markdown with optional yaml (`---`) or toml (`+++`) front matter.
+    /// the keys of the front matter become fields and the body becomes the `text` field.
+    pub fn content_from_markdown(text: &str) -> Content {
+        let text = text.trim_start_matches('\u{feff}');
+        let mut content = Content::new(None);
+
+        let (front_matter, body) = match Self::split_front_matter(text, "---") {
+            Some((front_matter, body)) => (
+                YamlLoader::load_from_str(front_matter)
+                    .ok()
+                    .and_then(|mut docs| (!docs.is_empty()).then(|| docs.remove(0))),
+                body,
+            ),
+            None => match Self::split_front_matter(text, "+++") {
+                Some((front_matter, body)) => (
+                    front_matter
+                        .parse::<toml::Table>()
+                        .ok()
+                        .map(|table| Self::yaml_from_toml(&toml::Value::Table(table))),
+                    body,
+                ),
+                None => (None, text),
+            },
+        };
+
+        if let Some(Yaml::Hash(hash)) = front_matter {
+            for (key, value) in hash.iter() {
+                let name = match key.as_str() {
+                    Some(name) => name.trim().to_lowercase(),
+                    None => continue,
+                };
+                let value = Self::string_from_yaml(value);
+                content
+                    .fields
+                    .insert(name.clone(), Field::new(&name, Some(value.trim())));
+            }
+        }
+
+        if !body.trim().is_empty() {
+            content
+                .fields
+                .insert("text".to_string(), Field::new("text", Some(body.trim())));
+        }
+
+        content
+    }
+
+    fn split_front_matter<'a>(text: &'a str, fence: &str) -> Option<(&'a str, &'a str)> {
+        let rest = text.strip_prefix(fence)?;
+        let rest = rest
+            .strip_prefix("\r\n")
+            .or_else(|| rest.strip_prefix('\n'))?;
+
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if line.trim_end() == fence {
+                return Some((&rest[..offset], &rest[offset + line.len()..]));
+            }
+            offset += line.len();
+        }
+        None
+    }
+
+    /// scalars are stored as is, lists of scalars as comma separated list like kirby tags
+    /// and everything else as yaml like kirby structure fields
+    fn string_from_yaml(yaml: &Yaml) -> String {
+        match yaml {
+            Yaml::String(s) | Yaml::Real(s) => s.to_string(),
+            Yaml::Integer(i) => i.to_string(),
+            Yaml::Boolean(b) => b.to_string(),
+            Yaml::Array(list)
+                if list
+                    .iter()
+                    .all(|item| !matches!(item, Yaml::Array(_) | Yaml::Hash(_))) =>
+            {
+                list.iter()
+                    .map(Self::string_from_yaml)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            }
+            Yaml::Array(_) | Yaml::Hash(_) => {
+                let mut out = String::new();
+                let mut emitter = YamlEmitter::new(&mut out);
+                match emitter.dump(yaml) {
+                    Ok(_) => out.trim_start_matches("---").trim().to_string(),
+                    Err(_) => "".to_string(),
+                }
+            }
+            _ => "".to_string(),
+        }
+    }
+
+    fn yaml_from_toml(value: &toml::Value) -> Yaml {
+        match value {
+            toml::Value::String(s) => Yaml::String(s.to_string()),
+            toml::Value::Integer(i) => Yaml::Integer(*i),
+            toml::Value::Float(f) => Yaml::Real(f.to_string()),
+            toml::Value::Boolean(b) => Yaml::Boolean(*b),
+            // kirby stores dates as `YYYY-MM-DD HH:MM:SS`
+            toml::Value::Datetime(d) => Yaml::String(d.to_string().replace('T', " ")),
+            toml::Value::Array(list) => {
+                Yaml::Array(list.iter().map(Self::yaml_from_toml).collect())
+            }
+            toml::Value::Table(table) => Yaml::Hash(
+                table
+                    .iter()
+                    .map(|(k, v)| (Yaml::String(k.to_string()), Self::yaml_from_toml(v)))
+                    .collect(),
+            ),
+        }
+    }
+
     pub fn extract_components(file_path: &PathBuf) -> (String, String, String, String, String) {
-        // 1. Extract `dir_path` without the filename
+        // 1. Extract `dir_path` without the filename. `_drafts` folders are not part of
+        // the id of the drafts, like `blog/_drafts/new-post` is `blog/new-post`
         let dir_path_buf = file_path
             .parent()
             .unwrap()
             .iter()
+            .filter(|segment| segment.to_str() != Some(DRAFTS))
             .map(|segment| {
                 let segment_str = segment.to_str().unwrap();
-
-                // Split the segment by '_'
-                match segment_str.split_once('_') {
-                    // If there's a right-hand side after `_`, and it's non-empty, keep it
-                    Some((_, right)) if !right.is_empty() => right.to_string(),
-                    // If no split or right-hand side is empty, keep the original segment
-                    _ => segment_str.to_string(),
-                }
+                let (_, slug) = Self::split_num(segment_str);
+                slug.to_string()
             })
             .collect::<PathBuf>();
         let dir_path = dir_path_buf.to_str().unwrap().to_string();
@@ -133,12 +426,9 @@ impl Kirby {
             .and_then(|s| s.to_str())
             .unwrap_or("");
 
-        // Extract `num` by splitting the last segment on "_"
-        let num = if last_segment.contains('_') {
-            last_segment.split('_').next().unwrap_or("").to_string()
-        } else {
-            "".to_string() // No number exists in the last segment
-        };
+        // Extract `num` from the digits before the first "_", which can be zero or a date like `20241017`
+        let (num, _) = Self::split_num(last_segment);
+        let num = num.to_string();
 
         // 3. Extract the `template` (filename before the first ".")
         let file_stem = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
@@ -167,6 +457,20 @@ impl Kirby {
         (dir_path, num, template, lang, filename)
     }
 
+    /// split a folder name like `1_whales` into the num and the slug, only digits are a num
+    fn split_num(segment: &str) -> (&str, &str) {
+        match segment.split_once('_') {
+            Some((num, slug))
+                if !num.is_empty()
+                    && !slug.is_empty()
+                    && num.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                (num, slug)
+            }
+            _ => ("", segment),
+        }
+    }
+
     pub fn load_recursive(
         site: &mut Site,
         root_path: &PathBuf, // Change to reference
@@ -189,10 +493,12 @@ impl Kirby {
             {
                 Self::load_recursive(site, root_path, &file_path)?;
             } else if file_path.is_file()
-                && file_path.extension().and_then(|ext| ext.to_str()) == Some("txt")
-            // TODO: Add support for markdown files?
+                && file_path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| site.extensions().iter().any(|e| e == ext))
             {
-                // If it's a .txt file, read its contents
+                // If it's a content file, read its contents
                 let mut file = fs::File::open(&file_path).map_err(DatabaseError::from)?;
                 let mut contents = String::new();
                 file.read_to_string(&mut contents)
@@ -200,23 +506,341 @@ impl Kirby {
 
                 // Add the model to the site (assuming this is defined elsewhere)
                 Self::add_model_to_site(site, root_path, &file_path, &contents);
+            } else if file_path.is_file()
+                && !file_path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            {
+                Self::add_file_to_site(site, root_path, &file_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn load_blueprints(site: &mut Site) -> Result<(), DatabaseError> {
+        let blueprints_path = Self::blueprints_folder_path(site);
+        site.blueprints_state = Self::folder_state(&blueprints_path);
+        if !blueprints_path.is_dir() {
+            site.blueprints.clear();
+            return Ok(());
+        }
+
+        // read all yml files first since blueprints can extend each other
+        let mut sources: BTreeMap<String, Yaml> = BTreeMap::new();
+        for entry in WalkDir::new(&blueprints_path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+        {
+            let file_path = entry.path();
+            if !matches!(
+                file_path.extension().and_then(|ext| ext.to_str()),
+                Some("yml") | Some("yaml")
+            ) {
+                continue;
+            }
+
+            let name = file_path
+                .strip_prefix(&blueprints_path)
+                .map_err(|e| DatabaseError::PathError(e.to_string()))?
+                .with_extension("")
+                .iter()
+                .map(|segment| segment.to_string_lossy().to_string())
+                .collect::<Vec<String>>()
+                .join("/");
+
+            let text = fs::read_to_string(file_path).map_err(DatabaseError::from)?;
+            match YamlLoader::load_from_str(&text) {
+                Ok(mut docs) if !docs.is_empty() => {
+                    sources.insert(name, docs.remove(0));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    println!(
+                        "Failed to parse blueprint at path '{}': {}",
+                        file_path.to_string_lossy(),
+                        e
+                    );
+                }
             }
         }
 
+        site.blueprints = sources
+            .iter()
+            .filter(|(name, _)| {
+                *name == "site"
+                    || name.starts_with("pages/")
+                    || name.starts_with("files/")
+                    || name.starts_with("users/")
+            })
+            .map(|(name, yaml)| (name.clone(), Blueprint::from_yaml(name, yaml, &sources)))
+            .collect();
+
         Ok(())
     }
 
+    /// set the declared kinds of the fields of all models again, after the blueprints changed
+    fn apply_blueprints(site: &mut Site) {
+        let blueprints: Vec<(String, Blueprint)> = site
+            .models
+            .iter()
+            .map(|(key, model)| {
+                (
+                    key.clone(),
+                    model.blueprint(site).cloned().unwrap_or_default(),
+                )
+            })
+            .collect();
+        for (key, blueprint) in blueprints {
+            if let Some(model) = site.models.get_mut(&key) {
+                model.apply_blueprint(&blueprint);
+            }
+        }
+    }
+
+    /// load the languages from kirby `site/languages/*.php` files or toml files with the same keys
+    pub fn load_languages(site: &mut Site) -> Result<(), DatabaseError> {
+        let languages_path = Self::languages_folder_path(site);
+        site.languages_state = Self::folder_state(&languages_path);
+        if !languages_path.is_dir() {
+            return Ok(());
+        }
+
+        let mut languages = vec![];
+        let mut variables = vec![];
+        for entry in fs::read_dir(&languages_path).map_err(DatabaseError::from)? {
+            let file_path = entry.map_err(DatabaseError::from)?.path();
+            let text = match file_path.extension().and_then(|ext| ext.to_str()) {
+                Some("php") | Some("toml") => {
+                    fs::read_to_string(&file_path).map_err(DatabaseError::from)?
+                }
+                // only the language variables, like `de.yml`
+                Some("yml") | Some("yaml") => {
+                    let text = fs::read_to_string(&file_path).map_err(DatabaseError::from)?;
+                    let code = file_path.file_stem().and_then(|stem| stem.to_str());
+                    match (code, YamlLoader::load_from_str(&text)) {
+                        (Some(code), Ok(docs)) if !docs.is_empty() => variables
+                            .push((code.to_lowercase(), Self::variables_from_yaml(&docs[0]))),
+                        _ => println!(
+                            "Failed to load language variables at path '{}'",
+                            file_path.to_string_lossy()
+                        ),
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let language = match file_path.extension().and_then(|ext| ext.to_str()) {
+                Some("php") => {
+                    PhpValue::parse_return(&text).and_then(|php| Self::language_from_php(&php))
+                }
+                _ => text
+                    .parse::<toml::Table>()
+                    .ok()
+                    .and_then(|toml| Self::language_from_toml(&toml)),
+            };
+
+            match language {
+                Some(language) => languages.push(language),
+                None => {
+                    println!(
+                        "Failed to load language at path '{}'",
+                        file_path.to_string_lossy()
+                    );
+                }
+            }
+        }
+
+        for (code, variables) in variables {
+            match languages.iter_mut().find(|language| language.code == code) {
+                Some(language) => language.translations.extend(variables),
+                None => println!("Language variables for unknown language '{}'", code),
+            }
+        }
+
+        site.languages = Languages::new(languages);
+        Ok(())
+    }
+
+    pub fn language_from_php(php: &PhpValue) -> Option<Language> {
+        let mut language = Language::new(php.get("code")?.as_str()?);
+        if let Some(name) = php.get("name").and_then(|name| name.as_str()) {
+            language.name = name.to_string();
+        }
+        language.default = php
+            .get("default")
+            .and_then(|d| d.as_bool())
+            .unwrap_or(false);
+        language.direction = Direction::from(
+            php.get("direction")
+                .and_then(|d| d.as_str())
+                .unwrap_or("ltr"),
+        );
+        language.locale = match php.get("locale") {
+            // [LC_ALL => 'de_DE'] or ['de_DE.utf-8', 'de_DE']
+            Some(locale @ PhpValue::Array(locales)) => locale
+                .get("LC_ALL")
+                .or_else(|| locales.first().map(|(_, locale)| locale))
+                .and_then(|locale| locale.as_string())
+                .unwrap_or_default(),
+            Some(locale) => locale.as_string().unwrap_or_default(),
+            None => language.code.clone(),
+        };
+        language.url = php.get("url").and_then(|url| url.as_string());
+        if let Some(PhpValue::Array(translations)) = php.get("translations") {
+            language.translations = translations
+                .iter()
+                .filter_map(|(key, value)| {
+                    let variable = match value {
+                        PhpValue::Array(entries) => {
+                            let texts = entries
+                                .iter()
+                                .filter_map(|(k, v)| Some((k.as_string()?, v.as_string()?)));
+                            let is_list = entries
+                                .iter()
+                                .enumerate()
+                                .all(|(i, (k, _))| *k == PhpValue::Int(i as i64));
+                            match is_list {
+                                true => Variable::Counted(texts.map(|(_, v)| v).collect()),
+                                false => Variable::Plural(texts.collect()),
+                            }
+                        }
+                        value => Variable::Text(value.as_string()?),
+                    };
+                    Some((key.as_string()?, variable))
+                })
+                .collect();
+        }
+        Some(language)
+    }
+
+    /// language variables from yaml, a list or map of texts for plurals
+    pub fn variables_from_yaml(yaml: &Yaml) -> BTreeMap<String, Variable> {
+        let text = |yaml: &Yaml| match yaml {
+            Yaml::String(s) | Yaml::Real(s) => Some(s.to_string()),
+            Yaml::Integer(i) => Some(i.to_string()),
+            Yaml::Boolean(b) => Some(b.to_string()),
+            _ => None,
+        };
+        let hash = match yaml.as_hash() {
+            Some(hash) => hash,
+            None => return BTreeMap::new(),
+        };
+        hash.iter()
+            .filter_map(|(key, value)| {
+                let variable = match value {
+                    Yaml::Array(texts) => {
+                        Variable::Counted(texts.iter().filter_map(text).collect())
+                    }
+                    Yaml::Hash(texts) => Variable::Plural(
+                        texts
+                            .iter()
+                            .filter_map(|(k, v)| Some((text(k)?, text(v)?)))
+                            .collect(),
+                    ),
+                    value => Variable::Text(text(value)?),
+                };
+                Some((text(key)?, variable))
+            })
+            .collect()
+    }
+
+    /// language variables from the `[translations]` table of a toml language file
+    pub fn variables_from_toml(toml: &toml::Table) -> BTreeMap<String, Variable> {
+        let text = |value: &toml::Value| match value {
+            toml::Value::String(s) => Some(s.to_string()),
+            value @ (toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_)) => {
+                Some(value.to_string())
+            }
+            _ => None,
+        };
+        toml.iter()
+            .filter_map(|(key, value)| {
+                let variable = match value {
+                    toml::Value::Array(texts) => {
+                        Variable::Counted(texts.iter().filter_map(text).collect())
+                    }
+                    toml::Value::Table(texts) => Variable::Plural(
+                        texts
+                            .iter()
+                            .filter_map(|(k, v)| Some((k.to_string(), text(v)?)))
+                            .collect(),
+                    ),
+                    value => Variable::Text(text(value)?),
+                };
+                Some((key.to_string(), variable))
+            })
+            .collect()
+    }
+
+    pub fn language_from_toml(toml: &toml::Table) -> Option<Language> {
+        let mut language = Language::new(toml.get("code")?.as_str()?);
+        if let Some(name) = toml.get("name").and_then(|name| name.as_str()) {
+            language.name = name.to_string();
+        }
+        language.default = toml
+            .get("default")
+            .and_then(|d| d.as_bool())
+            .unwrap_or(false);
+        language.direction = Direction::from(
+            toml.get("direction")
+                .and_then(|d| d.as_str())
+                .unwrap_or("ltr"),
+        );
+        language.locale = toml
+            .get("locale")
+            .and_then(|locale| locale.as_str())
+            .unwrap_or(&language.code)
+            .to_string();
+        language.url = toml
+            .get("url")
+            .and_then(|url| url.as_str())
+            .map(|url| url.to_string());
+        if let Some(translations) = toml.get("translations").and_then(|t| t.as_table()) {
+            language.translations = Self::variables_from_toml(translations);
+        }
+        Some(language)
+    }
+
+    pub fn languages_folder_path(site: &Site) -> PathBuf {
+        Self::site_folder_path(site).join("languages")
+    }
+
+    pub fn accounts_folder_path(site: &Site) -> PathBuf {
+        Self::site_folder_path(site).join("accounts")
+    }
+
+    pub fn site_folder_path(site: &Site) -> PathBuf {
+        // load from env variable
+        let dir = dotenvy::var("KIRBY_SITE")
+            .unwrap_or_else(|_| format!("{}/site", site.dir().to_str().unwrap()));
+        PathBuf::from(dir)
+    }
+
+    pub fn blueprints_folder_path(site: &Site) -> PathBuf {
+        Self::site_folder_path(site).join("blueprints")
+    }
+
     pub fn content_folder_path(site: &Site) -> PathBuf {
         // load from env variable
         let dir = dotenvy::var("KIRBY_CONTENT")
             .unwrap_or_else(|_| format!("{}/storage/content", site.dir().to_str().unwrap()));
         PathBuf::from(dir)
     }
+
+    pub fn media_folder_path(site: &Site) -> PathBuf {
+        let dir = dotenvy::var("KIRBY_MEDIA")
+            .unwrap_or_else(|_| format!("{}/storage/media", site.dir().to_str().unwrap()));
+        PathBuf::from(dir)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cms::site::Resolved;
 
     #[test]
     fn it_can_make_content_from_txt() {
@@ -227,6 +851,39 @@ mod tests {
         assert_eq!(content.fields.get("desc").unwrap().value(), "World");
     }
 
+    #[test]
+    fn it_can_make_content_from_markdown() {
+        let text = "---\nTitle: Hello\ntags: [rust, kirby]\nlinks:\n  - url: https://getkirby.com\n---\n\n# Hello\n\nWorld";
+        let content = Kirby::content_from_markdown(text);
+        assert_eq!(content.fields.len(), 4);
+        assert_eq!(content.fields.get("title").unwrap().value(), "Hello");
+        assert_eq!(content.fields.get("tags").unwrap().value(), "rust, kirby");
+        assert_eq!(
+            content.fields.get("links").unwrap().value(),
+            "- url: \"https://getkirby.com\""
+        );
+        assert_eq!(
+            content.fields.get("text").unwrap().value(),
+            "# Hello\n\nWorld"
+        );
+
+        let text = "+++\ntitle = \"Hello\"\ndate = 2024-10-17T12:00:00\n+++\nWorld";
+        let content = Kirby::content_from_markdown(text);
+        assert_eq!(content.fields.get("title").unwrap().value(), "Hello");
+        assert_eq!(
+            content.fields.get("date").unwrap().value(),
+            "2024-10-17 12:00:00"
+        );
+        assert_eq!(content.fields.get("text").unwrap().value(), "World");
+
+        let content = Kirby::content_from_markdown("Just text\n---\nwith a rule");
+        assert_eq!(content.fields.len(), 1);
+        assert_eq!(
+            content.fields.get("text").unwrap().value(),
+            "Just text\n---\nwith a rule"
+        );
+    }
+
     #[test]
     fn it_can_extract_components() {
         let file_path = PathBuf::from("content/1_some/default.en.txt");
@@ -244,6 +901,317 @@ mod tests {
         assert_eq!(template, "home");
         assert_eq!(lang, "en");
         assert_eq!(filename, "home.en.txt");
+
+        let file_path = PathBuf::from("blog/2_notes/post.de.md");
+        let (dir_path, num, template, lang, _) = Kirby::extract_components(&file_path);
+        assert_eq!(dir_path, "blog/notes");
+        assert_eq!(num, "2");
+        assert_eq!(template, "post");
+        assert_eq!(lang, "de");
+
+        let file_path = PathBuf::from("blog/_drafts/new_post/_drafts/0_child/post.md");
+        let (dir_path, num, _, _, _) = Kirby::extract_components(&file_path);
+        assert_eq!(dir_path, "blog/new_post/child");
+        assert_eq!(num, "0");
+
+        let file_path = PathBuf::from("blog/20241017_post/post.md");
+        let (dir_path, num, _, _, _) = Kirby::extract_components(&file_path);
+        assert_eq!(dir_path, "blog/post");
+        assert_eq!(num, "20241017");
+    }
+
+    #[test]
+    fn it_loads_languages_and_flags_unknown_ones() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let languages = temp_dir.path().join("site/languages");
+        fs::create_dir_all(&languages).unwrap();
+        fs::write(
+            languages.join("en.php"),
+            "<?php\n\nreturn [\n    'code' => 'en',\n    'default' => true,\n    'locale' => [LC_ALL => 'en_US'],\n    'name' => 'English',\n    'translations' => [\n        'read.more' => 'Read more',\n        'comments' => ['No comments', 'One comment', '{{ count }} comments'],\n    ],\n];",
+        )
+        .unwrap();
+        fs::write(
+            languages.join("ar.toml"),
+            "code = \"ar\"\nname = \"Arabic\"\ndirection = \"rtl\"\nlocale = \"ar_AE\"\n\n[translations]\n\"read.more\" = \"اقرأ المزيد\"\n\n[translations.comments]\nzero = \"لا تعليقات\"\nother = \"{{ count }} تعليق\"",
+        )
+        .unwrap();
+        fs::write(
+            languages.join("en.yml"),
+            "published: Published on {{ date }}\nposts:\n  one: One post\n  other: \"{{ count }} posts\"",
+        )
+        .unwrap();
+
+        let mut site = crate::site().dir(&temp_dir.path().to_path_buf()).build();
+        Kirby::load_languages(&mut site).unwrap();
+        assert_eq!(site.languages.codes(), vec!["en", "ar"]);
+        assert_eq!(site.default_language().unwrap().locale, "en_US");
+        assert!(site.language("ar").unwrap().is_rtl());
+
+        let languages = &site.languages;
+        assert_eq!(
+            languages.translate("ar", "read.more", &[]),
+            Some("اقرأ المزيد".to_string())
+        );
+        assert_eq!(
+            languages.translate_count("ar", "comments", 0, &[]),
+            Some("لا تعليقات".to_string())
+        );
+        assert_eq!(
+            languages.translate_count("en", "comments", 5, &[]),
+            Some("5 comments".to_string())
+        );
+        assert_eq!(
+            languages.translate("ar", "published", &[("date", "2024-10-17")]),
+            Some("Published on 2024-10-17".to_string())
+        );
+        assert_eq!(
+            languages.translate_count("en", "posts", 1, &[]),
+            Some("One post".to_string())
+        );
+
+        #[cfg(feature = "multi_language")]
+        {
+            let content = temp_dir.path().join("content/home");
+            fs::create_dir_all(&content).unwrap();
+            let known = content.join("home.en.txt");
+            let unknown = content.join("home.xx.txt");
+            fs::write(&known, "Title: Home").unwrap();
+            fs::write(&unknown, "Title: Phantom").unwrap();
+
+            let root = temp_dir.path().join("content");
+            Kirby::add_model_to_site(&mut site, &root, &known, "Title: Home");
+            Kirby::add_model_to_site(&mut site, &root, &unknown, "Title: Phantom");
+            assert_eq!(site.models.len(), 1);
+            assert_eq!(
+                site.unknown_languages
+                    .get(&unknown.to_string_lossy().to_string()),
+                Some(&"xx".to_string())
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "multi_language")]
+    fn it_loads_files_with_and_without_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("content");
+        let whales = root.join("blog/1_whales");
+        fs::create_dir_all(&whales).unwrap();
+        fs::write(whales.join("post.en.txt"), "Title: Whales").unwrap();
+        fs::write(whales.join("whale.jpg"), "").unwrap();
+        fs::write(
+            whales.join("whale.jpg.en.txt"),
+            "Sort: 1\n----\nAlt: A whale",
+        )
+        .unwrap();
+        fs::write(whales.join("report.pdf"), "").unwrap();
+        fs::write(whales.join(".DS_Store"), "").unwrap();
+
+        let mut site = crate::site().dir(&temp_dir.path().to_path_buf()).build();
+        site.languages = Languages::from_codes(&["en"]);
+        Kirby::load_recursive(&mut site, &root, &root).unwrap();
+
+        let page = site.page("en/blog/whales", None).unwrap();
+        let files = page.files(&site);
+        let names: Vec<String> = files.iter().filter_map(|file| file.filename()).collect();
+        assert_eq!(names, vec!["whale.jpg", "report.pdf"]);
+        assert_eq!(
+            files.first().unwrap().content().fields["alt"].value(),
+            "A whale"
+        );
+        assert_eq!(files.last().unwrap().page(&site), Some(page));
+    }
+
+    #[test]
+    #[cfg(feature = "multi_language")]
+    fn it_loads_image_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("content");
+        let whales = root.join("blog/1_whales");
+        fs::create_dir_all(&whales).unwrap();
+        fs::write(whales.join("post.en.txt"), "Title: Whales").unwrap();
+        image::RgbImage::new(40, 20)
+            .save(whales.join("whale.png"))
+            .unwrap();
+        image::RgbImage::new(10, 30)
+            .save(whales.join("dolphin.png"))
+            .unwrap();
+        fs::write(whales.join("dolphin.png.en.txt"), "Alt: A dolphin").unwrap();
+
+        let mut site = crate::site().dir(&temp_dir.path().to_path_buf()).build();
+        site.languages = Languages::from_codes(&["en"]);
+        Kirby::load_recursive(&mut site, &root, &root).unwrap();
+
+        let whale = site.file("blog/whales/whale.png", None).unwrap();
+        assert_eq!(whale.dimensions(), Some((40, 20)));
+        let dolphin = site.file("blog/whales/dolphin.png", None).unwrap();
+        assert_eq!(dolphin.dimensions(), Some((10, 30)));
+        let json = serde_json::to_value(dolphin).unwrap();
+        assert_eq!(json["image"]["orientation"], "portrait");
+        assert_eq!(site.images.len(), 2);
+        assert_eq!(
+            site.page("en/blog/whales", None).unwrap().image_meta(),
+            None
+        );
+    }
+
+    #[test]
+    fn it_loads_users() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let account = temp_dir.path().join("site/accounts/a1b2c3d4");
+        fs::create_dir_all(&account).unwrap();
+        fs::write(
+            account.join("index.php"),
+            "<?php\n\nreturn [\n    'email' => 'bastian@example.com',\n    'language' => 'en',\n    'name' => 'Bastian',\n    'role' => 'admin'\n];\n",
+        )
+        .unwrap();
+        fs::write(account.join("user.en.txt"), "Bio: Founder").unwrap();
+        fs::write(account.join("profile.jpg"), "jpg").unwrap();
+        let account = temp_dir.path().join("site/accounts/e5f6g7h8");
+        fs::create_dir_all(&account).unwrap();
+        fs::write(
+            account.join("index.php"),
+            "<?php return ['email' => 'nico@example.com'];",
+        )
+        .unwrap();
+
+        let mut site = crate::site().dir(&temp_dir.path().to_path_buf()).build();
+        site.languages = Languages::from_codes(&["en", "de"]);
+        Kirby::load_users(&mut site).unwrap();
+
+        assert_eq!(site.users(None).len(), 2);
+        assert_eq!(site.users(Some("de")).len(), 2);
+        let bastian = site.user("bastian@example.com", None).unwrap();
+        assert_eq!(bastian.id(), "a1b2c3d4");
+        assert_eq!(bastian.name(), "Bastian");
+        assert_eq!(bastian.role(), Some("admin"));
+        assert_eq!(bastian.content().fields["bio"].value(), "Founder");
+        let url = bastian.avatar_url(&site).unwrap();
+        assert!(url.ends_with("/profile.jpg"));
+        let path = url.trim_start_matches("http://localhost:8000/media/");
+        assert_eq!(site.resolve_avatar(path), Some(Resolved::Page(bastian)));
+        assert_eq!(
+            site.resolve_avatar("users/a1b2c3d4/0-0/profile.jpg"),
+            Some(Resolved::Redirect(bastian))
+        );
+
+        let nico = site.user("user://e5f6g7h8", Some("de")).unwrap();
+        assert_eq!(nico.role(), Some("nobody"));
+        assert_eq!(nico.avatar(), None);
+    }
+
+    #[test]
+    fn it_loads_blueprints() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pages = temp_dir.path().join("site/blueprints/pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(
+            pages.join("default.yml"),
+            "title: Default\nfields:\n  text:\n    type: textarea",
+        )
+        .unwrap();
+        fs::write(
+            pages.join("post.yml"),
+            "extends: pages/default\ntitle: Post\nfields:\n  date:\n    type: date",
+        )
+        .unwrap();
+
+        let mut site = crate::site().dir(&temp_dir.path().to_path_buf()).build();
+        Kirby::load_blueprints(&mut site).unwrap();
+        assert_eq!(site.blueprints.len(), 2);
+
+        let post = site.blueprint("pages/post").unwrap();
+        assert_eq!(post.fields.len(), 2);
+
+        let mut model = ModelBuilder::new()
+            .kind(&ModelKind::Page)
+            .template("post")
+            .content(&Kirby::content_from_string(
+                "Date: 2024-10-17\n----\nText: Hi",
+            ))
+            .build();
+        model.apply_blueprint(model.blueprint(&site).unwrap());
+        let date = model.content().fields.get("date").unwrap();
+        assert_eq!(date.kind().as_str(), "date");
+    }
+
+    #[test]
+    #[cfg(all(feature = "kirby_file_watcher", feature = "multi_language"))]
+    fn it_reloads_everything_when_the_languages_change() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let post = temp_dir.path().join("storage/content/1_post");
+        fs::create_dir_all(&post).unwrap();
+        fs::write(post.join("post.en.txt"), "Title: Post").unwrap();
+        fs::write(post.join("post.de.txt"), "Title: Beitrag").unwrap();
+        let languages = temp_dir.path().join("site/languages");
+        fs::create_dir_all(&languages).unwrap();
+        fs::write(
+            languages.join("en.php"),
+            "<?php return ['code' => 'en', 'default' => true, 'name' => 'English'];",
+        )
+        .unwrap();
+
+        let mut site = crate::site().dir(&temp_dir.path().to_path_buf()).build();
+        assert!(site.load(&vec![]));
+        assert!(site.page("de/post", None).is_none());
+        assert_eq!(site.unknown_languages.len(), 1);
+        assert!(site.changes().is_empty());
+
+        fs::write(
+            languages.join("de.php"),
+            "<?php return ['code' => 'de', 'name' => 'Deutsch'];",
+        )
+        .unwrap();
+        let changes = site.changes();
+        assert_eq!(changes, vec![languages.to_string_lossy().to_string()]);
+        assert!(site.load(&changes));
+        assert_eq!(site.languages.len(), 2);
+        let beitrag = site.page("de/post", None).unwrap();
+        assert_eq!(beitrag.content().fields["title"].value(), "Beitrag");
+        assert!(site.page("en/post", None).is_some());
+        assert!(site.unknown_languages.is_empty());
+        assert!(site.changes().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "kirby_file_watcher")]
+    fn it_reloads_changed_blueprints() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let post = temp_dir.path().join("storage/content/1_post");
+        fs::create_dir_all(&post).unwrap();
+        fs::write(post.join("post.txt"), "Title: Post\n----\nDate: 2024-10-17").unwrap();
+        let pages = temp_dir.path().join("site/blueprints/pages");
+        fs::create_dir_all(&pages).unwrap();
+        let blueprint = pages.join("post.yml");
+        fs::write(&blueprint, "title: Post\nfields:\n  date:\n    type: date").unwrap();
+
+        let mut site = crate::site().dir(&temp_dir.path().to_path_buf()).build();
+        assert!(site.load(&vec![]));
+        // any language, since the content file has no language suffix
+        let date = |site: &Site| {
+            let post = site.models.values().find(|model| model.is_page()).unwrap();
+            post.content().fields["date"].clone()
+        };
+        assert_eq!(date(&site).kind().as_str(), "date");
+        assert!(site.changes().is_empty());
+
+        fs::write(&blueprint, "title: Post\nfields:\n  date:\n    type: text").unwrap();
+        // the same number of files, so only the later modification tells the edit apart
+        let later = SystemTime::now() + std::time::Duration::from_secs(2);
+        fs::File::options()
+            .write(true)
+            .open(&blueprint)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let changes = site.changes();
+        assert_eq!(
+            changes,
+            vec![pages.parent().unwrap().to_string_lossy().to_string()]
+        );
+        assert!(site.load(&changes));
+        assert_eq!(date(&site).kind().as_str(), "text");
+        assert!(site.changes().is_empty());
     }
 }
 
@@ -251,24 +1219,89 @@ impl Database for Kirby {
     fn load(&self, site: &mut Site, changes: Vec<String>) -> Result<(), DatabaseError> {
         let root_path = Self::content_folder_path(site);
 
+        // the language of a model is part of its key, so all models are loaded again
+        let languages_path = Self::languages_folder_path(site);
+        if changes
+            .iter()
+            .any(|change| Path::new(change) == languages_path)
+        {
+            if !languages_path.is_dir() {
+                site.languages = Languages::new(vec![]);
+            }
+            site.models.clear();
+            site.reindex();
+            site.unknown_languages.clear();
+            site.last_modified = SystemTime::UNIX_EPOCH;
+            self.load(site, vec![])?;
+            site.prune_image_meta();
+            return Ok(());
+        }
+
         // if empty changes then load all from root_path
         if changes.is_empty() {
+            Self::load_languages(site)?;
+            Self::load_blueprints(site)?;
+            Self::load_users(site)?;
             Self::load_recursive(site, &root_path, &root_path)
         } else {
             // else load each changed dirs/files separately
+            let accounts_path = Self::accounts_folder_path(site);
+            let blueprints_path = Self::blueprints_folder_path(site);
             for change in changes {
-                Self::load_recursive(site, &root_path, &PathBuf::from(change))?;
+                let dir = PathBuf::from(change);
+                if dir == accounts_path {
+                    Self::load_users(site)?;
+                    continue;
+                }
+                if dir == blueprints_path {
+                    Self::load_blueprints(site)?;
+                    Self::apply_blueprints(site);
+                    continue;
+                }
+                // the models of deleted files are not replaced when loading the directory again
+                let stale: Vec<String> = site
+                    .models
+                    .iter()
+                    .filter(|(_, model)| PathBuf::from(model.root()).starts_with(&dir))
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                for key in stale {
+                    site.remove(&key);
+                }
+                Self::load_recursive(site, &root_path, &dir)?;
             }
+            site.prune_image_meta();
             Ok(())
         }
     }
 
+    fn content_dir(&self, site: &Site) -> PathBuf {
+        Self::content_folder_path(site)
+    }
+
+    fn media_dir(&self, site: &Site) -> PathBuf {
+        Self::media_folder_path(site)
+    }
+
     fn changes(&self, site: &Site) -> Vec<String> {
         #[cfg(not(feature = "kirby_file_watcher"))]
         return vec![];
 
         let root_path = Self::content_folder_path(site);
 
+        // the accounts, blueprints and languages are reloaded as a whole, also when the content
+        // is not changed
+        let folders = [
+            (Self::accounts_folder_path(site), site.accounts),
+            (Self::blueprints_folder_path(site), site.blueprints_state),
+            (Self::languages_folder_path(site), site.languages_state),
+        ];
+        let folders: Vec<String> = folders
+            .into_iter()
+            .filter(|(path, state)| Self::folder_state(path) != *state)
+            .map(|(path, _)| path.to_string_lossy().to_string())
+            .collect();
+
         // if a special file  exists in the root_path, then read it for the last updated timestamp of the kirby system
         if let Ok(mut file) = fs::File::open(root_path.join(".content-last-modified-at")) {
             let mut contents = String::new();
@@ -278,21 +1311,34 @@ impl Database for Kirby {
             // if the last_updated timestamp is less than or equal to the site's last_modified timestamp, then return empty changes
             if last_updated <= site.last_modified {
                 // println!("No changes detected");
-                return vec![];
+                return folders;
             }
         }
 
-        let state_from_models: HashMap<String, SystemTime> = site
+        let mut state_from_models: HashMap<String, SystemTime> = site
             .models
             .iter()
             .map(|(_, model)| (model.root(), model.last_modified()))
             .collect();
+        // the media files of files with metadata, which are only added or removed
+        for model in site.models.values().filter(|model| model.is_file()) {
+            if let Some(file) = model.file_root().filter(|file| *file != model.root()) {
+                if let Ok(modified) = fs::metadata(&file).and_then(|metadata| metadata.modified()) {
+                    state_from_models.insert(file, modified);
+                }
+            }
+        }
 
-        FileWatcher::new(
-            &root_path,
-            Some(&state_from_models),
-            Some(&vec!["txt".to_string()]), // kirby txt files only
-        )
-        .changes()
+        // the files of unknown languages are only loaded again when the languages change
+        for file in site.unknown_languages.keys() {
+            if let Ok(modified) = fs::metadata(file).and_then(|metadata| metadata.modified()) {
+                state_from_models.insert(file.clone(), modified);
+            }
+        }
+
+        // all files, since every file in the folder of a page is a file of the page
+        let mut changes = FileWatcher::new(&root_path, Some(&state_from_models), None).changes();
+        changes.extend(folders);
+        changes
     }
 }
@@ -1,8 +1,11 @@
 use crate::cms::site::Site;
 use std::fmt::{self, Debug, Formatter};
+use std::path::PathBuf;
 
 #[cfg(feature = "kirby")]
 pub mod kirby;
+#[cfg(feature = "kirby")]
+pub mod php;
 //pub mod mongodb;
 //pub mod redis;
 //pub mod sqlite;
@@ -10,6 +13,10 @@ pub mod kirby;
 pub trait Database: Send + Sync + Debug {
     fn load(&self, site: &mut Site, changes: Vec<String>) -> Result<(), DatabaseError>;
     fn changes(&self, site: &Site) -> Vec<String>;
+    /// the folder with the content and files of the pages
+    fn content_dir(&self, site: &Site) -> PathBuf;
+    /// the folder with the generated files like thumbs, served under `/media`
+    fn media_dir(&self, site: &Site) -> PathBuf;
     // fn refresh(&self, site: & Site) -> Result<Vec<String>, DatabaseError>;
     // TODO: save
 }
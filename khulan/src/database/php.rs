@@ -0,0 +1,298 @@
+/// Values of the plain php arrays kirby uses for config, language and account files.
+/// Only literals are supported, function calls are read as null and constants as their name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhpValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<(PhpValue, PhpValue)>),
+}
+
+impl PhpValue {
+    pub fn get(&self, key: &str) -> Option<&PhpValue> {
+        match self {
+            PhpValue::Array(entries) => entries
+                .iter()
+                .find(|(k, _)| k.as_string().as_deref() == Some(key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PhpValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            PhpValue::Bool(b) => Some(*b),
+            PhpValue::Int(i) => Some(*i != 0),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<(PhpValue, PhpValue)>> {
+        match self {
+            PhpValue::Array(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// scalars as string like php would cast them
+    pub fn as_string(&self) -> Option<String> {
+        match self {
+            PhpValue::String(s) => Some(s.to_string()),
+            PhpValue::Int(i) => Some(i.to_string()),
+            PhpValue::Float(f) => Some(f.to_string()),
+            PhpValue::Bool(true) => Some("1".to_string()),
+            PhpValue::Bool(false) => Some("".to_string()),
+            _ => None,
+        }
+    }
+
+    /// parse the array a php file returns, like `<?php return ['code' => 'en'];`
+    pub fn parse_return(text: &str) -> Option<PhpValue> {
+        let start = text.find("return")? + "return".len();
+        let mut parser = Parser {
+            chars: text[start..].chars().collect(),
+            pos: 0,
+        };
+        parser.value()
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => self.pos += 1,
+                Some('#') => self.skip_line(),
+                Some('/') if self.starts_with("//") => self.skip_line(),
+                Some('/') if self.starts_with("/*") => {
+                    while self.pos < self.chars.len() && !self.starts_with("*/") {
+                        self.pos += 1;
+                    }
+                    self.pos += 2;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == '\n' {
+                return;
+            }
+        }
+    }
+
+    fn value(&mut self) -> Option<PhpValue> {
+        self.skip_whitespace();
+        match self.peek()? {
+            '[' => {
+                self.pos += 1;
+                self.array(']')
+            }
+            '\'' | '"' => self.string(),
+            c if c.is_ascii_digit() || c == '-' || c == '.' => self.number(),
+            _ => {
+                let ident = self.identifier();
+                self.skip_whitespace();
+                match ident.to_lowercase().as_str() {
+                    "array" if self.peek() == Some('(') => {
+                        self.pos += 1;
+                        self.array(')')
+                    }
+                    _ if self.peek() == Some('(') => {
+                        self.skip_balanced();
+                        Some(PhpValue::Null)
+                    }
+                    "true" => Some(PhpValue::Bool(true)),
+                    "false" => Some(PhpValue::Bool(false)),
+                    "null" => Some(PhpValue::Null),
+                    "" => None,
+                    _ => Some(PhpValue::String(ident)),
+                }
+            }
+        }
+    }
+
+    fn array(&mut self, close: char) -> Option<PhpValue> {
+        let mut entries = vec![];
+        let mut index = 0;
+        loop {
+            self.skip_whitespace();
+            if self.peek()? == close {
+                self.pos += 1;
+                return Some(PhpValue::Array(entries));
+            }
+
+            let first = self.value()?;
+            self.skip_whitespace();
+            if self.starts_with("=>") {
+                self.pos += 2;
+                let value = self.value()?;
+                if let PhpValue::Int(i) = first {
+                    index = i + 1;
+                }
+                entries.push((first, value));
+            } else {
+                entries.push((PhpValue::Int(index), first));
+                index += 1;
+            }
+
+            self.skip_whitespace();
+            if self.peek() == Some(',') {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<PhpValue> {
+        let quote = self.peek()?;
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            let c = self.peek()?;
+            self.pos += 1;
+            match c {
+                '\\' => {
+                    let next = self.peek()?;
+                    self.pos += 1;
+                    match (quote, next) {
+                        (_, '\\') => value.push('\\'),
+                        ('\'', '\'') => value.push('\''),
+                        ('"', '"') => value.push('"'),
+                        ('"', 'n') => value.push('\n'),
+                        ('"', 't') => value.push('\t'),
+                        ('"', '$') => value.push('$'),
+                        (_, other) => {
+                            value.push('\\');
+                            value.push(other);
+                        }
+                    }
+                }
+                c if c == quote => return Some(PhpValue::String(value)),
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<PhpValue> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit() || c == '-' || c == '.' || c == '_')
+        {
+            self.pos += 1;
+        }
+        let number: String = self.chars[start..self.pos]
+            .iter()
+            .filter(|c| **c != '_')
+            .collect();
+        match number.parse::<i64>() {
+            Ok(i) => Some(PhpValue::Int(i)),
+            Err(_) => number.parse::<f64>().ok().map(PhpValue::Float),
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_alphanumeric() || c == '_' || c == '\\' || c == ':')
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn skip_balanced(&mut self) {
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                '\'' | '"' => {
+                    self.string();
+                    continue;
+                }
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos += 1;
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_kirby_language_files() {
+        let php = r#"<?php
+
+return [
+    'code' => 'de',
+    'default' => false,
+    'direction' => "ltr",
+    // the locale can be a string or an array
+    'locale' => array(
+        LC_ALL => 'de_DE.utf-8'
+    ),
+    'name' => 'Deutsch',
+    'url' => NULL,
+    'translations' => [
+        'read.more' => 'Weiterlesen',
+        'it\'s' => 'es ist',
+    ],
+    'env' => env('LANG'),
+    'list' => ['a', 'b', 3 => 'c', 'd'],
+];"#;
+        let value = PhpValue::parse_return(php).unwrap();
+        assert_eq!(value.get("code").unwrap().as_str(), Some("de"));
+        assert_eq!(value.get("default").unwrap().as_bool(), Some(false));
+        assert_eq!(value.get("direction").unwrap().as_str(), Some("ltr"));
+        assert_eq!(
+            value.get("locale").unwrap().get("LC_ALL").unwrap().as_str(),
+            Some("de_DE.utf-8")
+        );
+        assert_eq!(value.get("url"), Some(&PhpValue::Null));
+        assert_eq!(value.get("env"), Some(&PhpValue::Null));
+
+        let translations = value.get("translations").unwrap();
+        assert_eq!(
+            translations.get("read.more").unwrap().as_str(),
+            Some("Weiterlesen")
+        );
+        assert_eq!(translations.get("it's").unwrap().as_str(), Some("es ist"));
+
+        let list = value.get("list").unwrap().as_array().unwrap();
+        assert_eq!(list[1], (PhpValue::Int(1), PhpValue::String("b".into())));
+        assert_eq!(list[3], (PhpValue::Int(4), PhpValue::String("d".into())));
+    }
+}
@@ -1,13 +1,19 @@
 #[macro_use]
 extern crate rocket;
+// lets the derive macros refer to `::khulan` from within this crate
+extern crate self as khulan;
 
 use crate::cms::site::SiteBuilder;
 
 pub mod cms;
 pub mod database;
 pub mod routes;
+pub mod templates;
 pub mod watcher;
 
+pub use cms::page::{KhulanPage, Page};
+pub use khulan_derive::KhulanPage;
+
 pub fn site() -> SiteBuilder {
     SiteBuilder::new()
 }
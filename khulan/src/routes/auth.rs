@@ -0,0 +1,511 @@
+use crate::cms::model::Model;
+use crate::cms::site::Site;
+use crate::routes::refresh;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use rocket::form::{Form, FromForm};
+use rocket::http::{Cookie, CookieJar, SameSite, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::Redirect;
+use rocket::serde::json::Json;
+use rocket::State;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// the private cookie of the session with the id of the user, signed and encrypted by rocket
+pub const SESSION_COOKIE: &str = "khulan_session";
+
+/// the private cookie with the token that forms have to send back
+pub const CSRF_COOKIE: &str = "khulan_csrf";
+
+/// a hash of the cost of the panel, verified for unknown emails so logins take as long
+/// as for accounts and do not tell which emails have one
+const DUMMY_HASH: &str = "$2y$10$gmdSaF3MAFMP9zWGreGCp.5mUWAA9viIWAA6ffzJEnDAycnY7njM2";
+
+/// how long users stay logged in, like kirby's two weeks when remembered
+pub const SESSION_LIFETIME: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// The logged in user of the request, from an account in `site/accounts`. Forwards with
+/// `401` if there is no session, it has expired or the password has changed since.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct User {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    pub role: String,
+}
+
+impl User {
+    fn from_model(model: &Model) -> Self {
+        User {
+            id: model.id().to_string(),
+            email: model.email().to_string(),
+            name: model.name().to_string(),
+            role: model.role().unwrap_or("").to_string(),
+        }
+    }
+}
+
+/// the session of the user like `a1b2c3d4:1700000000:<fingerprint>`
+fn session_value(user: &Model, issued: u64) -> Option<String> {
+    Some(format!(
+        "{}:{}:{}",
+        user.id(),
+        issued,
+        user.password_fingerprint()?
+    ))
+}
+
+fn session_user(site: &Site, value: &str, now: u64) -> Option<User> {
+    let mut parts = value.splitn(3, ':');
+    let (id, issued, fingerprint) = (parts.next()?, parts.next()?, parts.next()?);
+    let issued: u64 = issued.parse().ok()?;
+    if now.saturating_sub(issued) > SESSION_LIFETIME.as_secs() {
+        return None;
+    }
+    let user = site.user(id, None).filter(|user| user.id() == id)?;
+    (user.password_fingerprint().as_deref() == Some(fingerprint)).then(|| User::from_model(user))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for User {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        // once per request, as several guards may ask for the user
+        let user = request.local_cache(|| {
+            let cookie = request.cookies().get_private(SESSION_COOKIE)?;
+            let site_state = request.rocket().state::<Arc<RwLock<Site>>>()?;
+            // like a role changed in the panel, also for routes that do not load changes
+            refresh(site_state);
+            let site = site_state.read().unwrap();
+            session_user(&site, cookie.value(), now())
+        });
+        match user {
+            Some(user) => Outcome::Success(user.clone()),
+            None => Outcome::Forward(Status::Unauthorized),
+        }
+    }
+}
+
+/// the roles of a `Role` guard, like `Admin`
+pub trait Roles {
+    const ROLES: &'static [&'static str];
+}
+
+/// the admins, which kirby creates as the first user
+pub struct Admin;
+
+impl Roles for Admin {
+    const ROLES: &'static [&'static str] = &["admin"];
+}
+
+/// The logged in user with one of the roles of `R`. Forwards with `401` if there is no user
+/// and with `403` if the user has another role.
+#[derive(Debug, Clone)]
+pub struct Role<R: Roles> {
+    pub user: User,
+    roles: PhantomData<fn() -> R>,
+}
+
+#[rocket::async_trait]
+impl<'r, R: Roles> FromRequest<'r> for Role<R> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = match request.guard::<User>().await {
+            Outcome::Success(user) => user,
+            _ => return Outcome::Forward(Status::Unauthorized),
+        };
+        match R::ROLES.contains(&user.role.as_str()) {
+            true => Outcome::Success(Role {
+                user,
+                roles: PhantomData,
+            }),
+            false => Outcome::Forward(Status::Forbidden),
+        }
+    }
+}
+
+/// The token against cross site requests, kept in a private cookie and created on the
+/// first request. Forms send it back in their `csrf` field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// compares in constant time, so the token can not be guessed by timing
+    pub fn verify(&self, token: &str) -> bool {
+        let (a, b) = (self.0.as_bytes(), token.as_bytes());
+        a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CsrfToken {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let cookies = request.cookies();
+        if let Some(cookie) = cookies.get_private(CSRF_COOKIE) {
+            return Outcome::Success(CsrfToken(cookie.value().to_string()));
+        }
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+        cookies.add_private(
+            Cookie::build((CSRF_COOKIE, token.clone()))
+                .path("/")
+                .same_site(SameSite::Lax)
+                .http_only(true),
+        );
+        Outcome::Success(CsrfToken(token))
+    }
+}
+
+/// The failed logins by client and by account, which block further logins for a while
+/// against guessing passwords. Managed by rocket like the site.
+pub struct Throttle {
+    trials: Mutex<HashMap<String, Vec<Instant>>>,
+    limit: usize,
+    window: Duration,
+    /// the header with the ip of the client set by a proxy in front of khulan
+    ip_header: Option<String>,
+}
+
+impl Throttle {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Throttle {
+            trials: Mutex::new(HashMap::new()),
+            limit,
+            window,
+            ip_header: None,
+        }
+    }
+
+    /// Trust the header of a proxy like `X-Real-IP` for the ip of the client. Without it,
+    /// the address of the connection is used, as clients can send any header.
+    pub fn ip_header(mut self, header: &str) -> Self {
+        self.ip_header = Some(header.to_string());
+        self
+    }
+
+    /// if the key has failed too often within the window, like `ip:127.0.0.1`
+    pub fn is_blocked(&self, key: &str) -> bool {
+        let trials = self.trials.lock().unwrap();
+        trials.get(key).is_some_and(|trials| {
+            trials
+                .iter()
+                .filter(|trial| trial.elapsed() < self.window)
+                .count()
+                >= self.limit
+        })
+    }
+
+    pub fn fail(&self, key: &str) {
+        let mut trials = self.trials.lock().unwrap();
+        // forget the old trials of all keys, so the map does not grow forever
+        trials.retain(|_, trials| {
+            trials.retain(|trial| trial.elapsed() < self.window);
+            !trials.is_empty()
+        });
+        trials
+            .entry(key.to_string())
+            .or_default()
+            .push(Instant::now());
+    }
+
+    pub fn reset(&self, key: &str) {
+        self.trials.lock().unwrap().remove(key);
+    }
+}
+
+impl Default for Throttle {
+    /// ten failed logins per hour, like kirby's defaults
+    fn default() -> Self {
+        Throttle::new(10, Duration::from_secs(60 * 60))
+    }
+}
+
+/// The ip of the client to throttle logins by, from the connection or from the header
+/// that the throttle trusts. Unlike rocket's `IpAddr` guard, `X-Real-IP` is not trusted
+/// by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientIp {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let header = request
+            .rocket()
+            .state::<Throttle>()
+            .and_then(|throttle| throttle.ip_header.as_deref());
+        // the last address of a list like `X-Forwarded-For` is the one the proxy has seen
+        let forwarded = header
+            .and_then(|header| request.headers().get_one(header))
+            .and_then(|value| value.rsplit(',').next()?.trim().parse().ok());
+        Outcome::Success(ClientIp(
+            forwarded.or(request.remote().map(|remote| remote.ip())),
+        ))
+    }
+}
+
+#[derive(Debug, FromForm)]
+pub struct Login<'r> {
+    pub email: &'r str,
+    pub password: &'r str,
+    pub csrf: &'r str,
+    /// the path to redirect to after the login, like the members only page
+    pub next: Option<&'r str>,
+}
+
+#[derive(Debug, FromForm)]
+pub struct Logout<'r> {
+    pub csrf: &'r str,
+}
+
+/// only paths on this site, so the login can not redirect to other sites
+fn local_path(next: Option<&str>) -> &str {
+    match next {
+        Some(next) if next.starts_with('/') && !next.starts_with("//") && !next.contains('\\') => {
+            next
+        }
+        _ => "/",
+    }
+}
+
+#[post("/login", data = "<login>")]
+pub fn login(
+    login: Form<Login<'_>>,
+    csrf: CsrfToken,
+    client: ClientIp,
+    cookies: &CookieJar<'_>,
+    throttle: &State<Throttle>,
+    site_state: &State<Arc<RwLock<Site>>>,
+) -> Result<Redirect, Status> {
+    if !csrf.verify(login.csrf) {
+        return Err(Status::Forbidden);
+    }
+    let email = format!("email:{}", login.email.trim().to_lowercase());
+    // clients without a known ip are only throttled by the account, not all as one
+    let keys: Vec<String> = client
+        .0
+        .map(|ip| format!("ip:{}", ip))
+        .into_iter()
+        .chain([email.clone()])
+        .collect();
+    if keys.iter().any(|key| throttle.is_blocked(key)) {
+        return Err(Status::TooManyRequests);
+    }
+
+    refresh(site_state);
+
+    let site = site_state.read().unwrap();
+    let session = match site.user(login.email, None) {
+        Some(user) => Some(user)
+            .filter(|user| user.verify_password(login.password))
+            .and_then(|user| session_value(user, now())),
+        None => {
+            let _ = bcrypt::verify(login.password, DUMMY_HASH);
+            None
+        }
+    };
+    let session = match session {
+        Some(session) => session,
+        None => {
+            keys.iter().for_each(|key| throttle.fail(key));
+            return Err(Status::Unauthorized);
+        }
+    };
+
+    // the client stays throttled, so one account can not unblock guessing others
+    throttle.reset(&email);
+    cookies.add_private(
+        Cookie::build((SESSION_COOKIE, session))
+            .path("/")
+            .same_site(SameSite::Lax)
+            .http_only(true)
+            .max_age(rocket::time::Duration::seconds(
+                SESSION_LIFETIME.as_secs() as i64
+            )),
+    );
+    // a new token for the session
+    cookies.remove_private(Cookie::build(CSRF_COOKIE).path("/"));
+    Ok(Redirect::to(local_path(login.next).to_string()))
+}
+
+#[post("/logout", data = "<logout>")]
+pub fn logout(
+    logout: Form<Logout<'_>>,
+    csrf: CsrfToken,
+    cookies: &CookieJar<'_>,
+) -> Result<Redirect, Status> {
+    if !csrf.verify(logout.csrf) {
+        return Err(Status::Forbidden);
+    }
+    cookies.remove_private(Cookie::build(SESSION_COOKIE).path("/"));
+    cookies.remove_private(Cookie::build(CSRF_COOKIE).path("/"));
+    Ok(Redirect::to("/"))
+}
+
+#[derive(Debug, Serialize)]
+pub struct Auth {
+    pub user: Option<User>,
+    /// the token for the `csrf` field of the login and logout forms
+    pub csrf: String,
+}
+
+#[get("/api/auth")]
+pub fn api_auth(user: Option<User>, csrf: CsrfToken) -> Json<Auth> {
+    Json(Auth {
+        user,
+        csrf: csrf.as_str().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::local::blocking::Client;
+
+    #[test]
+    fn it_throttles_failed_logins() {
+        let throttle = Throttle::new(2, Duration::from_secs(60));
+        throttle.fail("ip:127.0.0.1");
+        assert!(!throttle.is_blocked("ip:127.0.0.1"));
+        throttle.fail("ip:127.0.0.1");
+        assert!(throttle.is_blocked("ip:127.0.0.1"));
+        assert!(!throttle.is_blocked("email:bastian@example.com"));
+        throttle.reset("ip:127.0.0.1");
+        assert!(!throttle.is_blocked("ip:127.0.0.1"));
+
+        let throttle = Throttle::new(1, Duration::ZERO);
+        throttle.fail("ip:127.0.0.1");
+        assert!(!throttle.is_blocked("ip:127.0.0.1"));
+    }
+
+    #[test]
+    fn it_only_redirects_to_local_paths() {
+        assert_eq!(local_path(Some("/members")), "/members");
+        assert_eq!(local_path(Some("//example.com")), "/");
+        assert_eq!(local_path(Some("/\\example.com")), "/");
+        assert_eq!(local_path(Some("https://example.com")), "/");
+        assert_eq!(local_path(None), "/");
+    }
+
+    #[get("/admin")]
+    fn admin(admin: Role<Admin>) -> String {
+        admin.user.id
+    }
+
+    #[test]
+    fn it_logs_in_with_the_password_of_the_account() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let account = temp_dir.path().join("site/accounts/a1b2c3d4");
+        std::fs::create_dir_all(&account).unwrap();
+        std::fs::write(
+            account.join("index.php"),
+            "<?php return ['email' => 'bastian@example.com', 'role' => 'editor'];",
+        )
+        .unwrap();
+        let hash = bcrypt::hash("secret", 4).unwrap();
+        std::fs::write(account.join(".htpasswd"), hash).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("content")).unwrap();
+
+        let mut site = crate::site().dir(&temp_dir.path().to_path_buf()).build();
+        site.load(&vec![]);
+        let rocket = rocket::build()
+            .manage(Arc::new(RwLock::new(site)))
+            .manage(Throttle::new(2, Duration::from_secs(60)))
+            .mount("/", routes![login, logout, api_auth, admin]);
+        let client = Client::tracked(rocket).unwrap();
+        let remote = "127.0.0.1:8000".parse().unwrap();
+
+        let auth = |client: &Client| -> serde_json::Value {
+            client.get("/api/auth").dispatch().into_json().unwrap()
+        };
+        let csrf = auth(&client)["csrf"].as_str().unwrap().to_string();
+        let form = |password: &str, csrf: &str| {
+            format!("email=Bastian%40example.com&password={password}&csrf={csrf}&next=%2Fmembers")
+        };
+        let post = |body: String| {
+            client
+                .post("/login")
+                .remote(remote)
+                .header(rocket::http::ContentType::Form)
+                .body(body)
+                .dispatch()
+                .status()
+        };
+
+        assert_eq!(post(form("secret", "forged")), Status::Forbidden);
+        assert_eq!(post(form("wrong", &csrf)), Status::Unauthorized);
+        assert_eq!(auth(&client)["user"], serde_json::Value::Null);
+        // unknown emails take as long, with a hash bcrypt can verify
+        assert!(bcrypt::verify("secret", DUMMY_HASH).is_ok_and(|valid| !valid));
+
+        let response = client
+            .post("/login")
+            .remote(remote)
+            .header(rocket::http::ContentType::Form)
+            .body(form("secret", &csrf))
+            .dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+        assert_eq!(response.headers().get_one("Location"), Some("/members"));
+        let auth = auth(&client);
+        assert_eq!(auth["user"]["id"], "a1b2c3d4");
+        assert_eq!(auth["user"]["role"], "editor");
+        assert_ne!(auth["csrf"], csrf.as_str());
+        assert_eq!(client.get("/admin").dispatch().status(), Status::Forbidden);
+
+        // the role is changed in the panel
+        std::fs::write(
+            account.join("index.php"),
+            "<?php return ['email' => 'bastian@example.com', 'role' => 'admin'];",
+        )
+        .unwrap();
+        assert_eq!(client.get("/admin").dispatch().status(), Status::Ok);
+
+        // a changed password ends the session
+        let hash = bcrypt::hash("changed", 4).unwrap();
+        std::fs::write(account.join(".htpasswd"), hash).unwrap();
+        assert_eq!(
+            client.get("/admin").dispatch().status(),
+            Status::Unauthorized
+        );
+
+        // blocked after too many failed logins, even with the right password
+        let csrf = auth["csrf"].as_str().unwrap();
+        assert_eq!(post(form("wrong", csrf)), Status::Unauthorized);
+        assert_eq!(post(form("changed", csrf)), Status::TooManyRequests);
+
+        // also for other accounts, as the ip of the header is not trusted
+        let response = client
+            .post("/login")
+            .remote(remote)
+            .header(rocket::http::ContentType::Form)
+            .header(rocket::http::Header::new("X-Real-IP", "10.0.0.1"))
+            .body(format!(
+                "email=nico%40example.com&password=secret&csrf={csrf}"
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::TooManyRequests);
+    }
+}
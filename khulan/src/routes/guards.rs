@@ -0,0 +1,114 @@
+use crate::cms::deserializer::DeserializeError;
+use crate::cms::page::{KhulanPage, Page};
+use crate::cms::site::{Resolved, Site};
+use crate::routes::auth::User;
+use crate::routes::refresh;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use serde::de::Error;
+use std::sync::{Arc, RwLock};
+
+/// the cookie that remembers the language of the last visited page
+pub const LANGUAGE_COOKIE: &str = "language";
+
+/// The language a visitor prefers, from the language cookie or else the
+/// `Accept-Language` header. Only languages of the site are considered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreferredLanguage {
+    pub code: Option<String>,
+    /// if the language is from the cookie, so the visitor has chosen it before
+    pub chosen: bool,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for PreferredLanguage {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let site_state = match request.rocket().state::<Arc<RwLock<Site>>>() {
+            Some(site_state) => site_state,
+            None => return Outcome::Success(PreferredLanguage::default()),
+        };
+        let site = site_state.read().unwrap();
+
+        // the domain of a language decides, like `example.de`
+        let host = request.host().map(|host| host.domain().as_str());
+        if let Some(language) = host.and_then(|host| site.language_of_host(host)) {
+            return Outcome::Success(PreferredLanguage {
+                code: Some(language.code().to_string()),
+                chosen: true,
+            });
+        }
+
+        if let Some(cookie) = request.cookies().get(LANGUAGE_COOKIE) {
+            if site.languages.contains(cookie.value()) {
+                return Outcome::Success(PreferredLanguage {
+                    code: Some(cookie.value().to_string()),
+                    chosen: true,
+                });
+            }
+        }
+
+        let code = request
+            .headers()
+            .get_one("Accept-Language")
+            .and_then(|header| site.languages.negotiate(header))
+            .map(|language| language.code().to_string());
+        Outcome::Success(PreferredLanguage {
+            code,
+            chosen: false,
+        })
+    }
+}
+
+/// Resolves the page from the request path. Forwards if there is no page
+/// at the path or if it uses another template than the one bound to `T`,
+/// and with `401` for members only pages without a logged in user.
+#[rocket::async_trait]
+impl<'r, T: KhulanPage + Send> FromRequest<'r> for Page<T> {
+    type Error = DeserializeError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let site_state = match request.rocket().state::<Arc<RwLock<Site>>>() {
+            Some(site_state) => site_state,
+            None => {
+                return Outcome::Error((
+                    Status::InternalServerError,
+                    DeserializeError::custom("the site is not managed by rocket"),
+                ))
+            }
+        };
+
+        // routes with this guard may be ranked before `index`, so it loads the changes itself
+        refresh(site_state);
+        // before the lock on the site, as the user is checked against it
+        let user = request.guard::<User>().await.succeeded();
+        let path = request.uri().path().url_decode_lossy().to_string();
+        let site = site_state.read().unwrap();
+        // the translated path on the domain of the request
+        let host = request.host().map(|host| host.domain().as_str());
+        let path = match site.resolve_at(host, &path) {
+            Some(Resolved::Page(model)) => model.path(),
+            _ => path,
+        };
+
+        let visible = site
+            .page(&path, None)
+            .is_some_and(|model| site.is_visible(model));
+        if !visible {
+            return Outcome::Forward(Status::NotFound);
+        }
+        let members_only = site
+            .page(&path, None)
+            .is_some_and(|model| site.is_members_only(model));
+        if members_only && user.is_none() {
+            return Outcome::Forward(Status::Unauthorized);
+        }
+
+        match Page::<T>::find(&site, &path) {
+            Some(Ok(page)) => Outcome::Success(page),
+            Some(Err(e)) => Outcome::Error((Status::InternalServerError, e)),
+            None => Outcome::Forward(Status::NotFound),
+        }
+    }
+}
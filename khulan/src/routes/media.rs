@@ -0,0 +1,259 @@
+use crate::cms::model::Model;
+use crate::cms::site::Site;
+use chrono::{DateTime, Utc};
+use rocket::http::{ContentType, Status};
+use rocket::response::{self, Responder, Response};
+use rocket::Request;
+use std::fs;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// the most bytes sent for a range, browsers request the rest of a video in parts
+const MAX_RANGE: u64 = 8 * 1024 * 1024;
+
+/// A file of the content folder, with caching headers and support for range requests
+#[derive(Debug)]
+pub struct MediaFile {
+    path: PathBuf,
+    content_type: ContentType,
+    etag: String,
+    last_modified: SystemTime,
+    len: u64,
+    /// only for logged in users, so shared caches like cdns must not store it
+    private: bool,
+}
+
+impl MediaFile {
+    /// the file of the model, if it is inside the content folder
+    pub fn open(site: &Site, file: &Model) -> Option<Self> {
+        let content_type = file
+            .mime()
+            .and_then(|mime| ContentType::parse_flexible(&mime))
+            .unwrap_or(ContentType::Binary);
+        Self::open_in(
+            &site.content_dir(),
+            Path::new(&file.file_root()?),
+            content_type,
+            &file.media_hash()?,
+        )
+    }
+
+    /// the avatar of the user in the folder of its account
+    pub fn avatar(user: &Model) -> Option<Self> {
+        let avatar = user.avatar()?;
+        let content_type = ContentType::from_extension(&avatar.extension()?.to_string_lossy())
+            .unwrap_or(ContentType::Binary);
+        Self::open_in(
+            &user.account_dir()?,
+            &avatar,
+            content_type,
+            &user.avatar_hash()?,
+        )
+    }
+
+    /// a generated file like a thumb, if it is inside the media folder
+    pub fn generated(site: &Site, path: &Path) -> Option<Self> {
+        let content_type = ContentType::from_extension(&path.extension()?.to_string_lossy())
+            .unwrap_or(ContentType::Binary);
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs();
+        let hash = format!(
+            "{:08x}-{}",
+            crc32fast::hash(path.to_string_lossy().as_bytes()),
+            modified
+        );
+        Self::open_in(&site.media_dir(), path, content_type, &hash)
+    }
+
+    fn open_in(root: &Path, path: &Path, content_type: ContentType, hash: &str) -> Option<Self> {
+        let path = fs::canonicalize(path).ok()?;
+        let root = fs::canonicalize(root).ok()?;
+        if !path.starts_with(&root) || !path.is_file() {
+            return None;
+        }
+        let metadata = fs::metadata(&path).ok()?;
+        Some(Self {
+            content_type,
+            etag: format!("\"{}-{:x}\"", hash, metadata.len()),
+            last_modified: metadata.modified().ok()?,
+            len: metadata.len(),
+            path,
+            private: false,
+        })
+    }
+
+    /// the file of a members only page, which is not stored by caches
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    fn is_modified_since(&self, since: &str) -> bool {
+        let modified = self
+            .last_modified
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or(0);
+        DateTime::parse_from_rfc2822(since).map_or(true, |since| modified > since.timestamp())
+    }
+}
+
+/// the date like `Thu, 17 Oct 2024 12:00:00 GMT` for http headers
+pub fn http_date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+/// The inclusive byte range of a `Range` header like `bytes=0-499`, `bytes=500-` or
+/// `bytes=-500`. None for headers that are ignored like multiple ranges and an error
+/// for ranges outside of the file.
+pub fn parse_range(header: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let range = header.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return None,
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().ok()?;
+            (len.saturating_sub(suffix), len.saturating_sub(1))
+        }
+        (start, "") => (start.parse::<u64>().ok()?, len.saturating_sub(1)),
+        (start, end) => (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?),
+    };
+    if start > end || start >= len || len == 0 {
+        return Some(Err(()));
+    }
+    Some(Ok((start, end.min(len - 1))))
+}
+
+impl<'r> Responder<'r, 'static> for MediaFile {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let headers = request.headers();
+        let last_modified = http_date(self.last_modified);
+
+        let mut response = Response::build();
+        response
+            .header(self.content_type.clone())
+            .raw_header("ETag", self.etag.clone())
+            .raw_header("Last-Modified", last_modified.clone())
+            .raw_header("Accept-Ranges", "bytes")
+            // the url changes with the file
+            .raw_header(
+                "Cache-Control",
+                match self.private {
+                    true => "private, no-store",
+                    false => "public, max-age=31536000, immutable",
+                },
+            );
+
+        let not_modified = match headers.get_one("If-None-Match") {
+            Some(etags) => etags.split(',').any(|etag| {
+                let etag = etag.trim().trim_start_matches("W/");
+                etag == self.etag || etag == "*"
+            }),
+            None => headers
+                .get_one("If-Modified-Since")
+                .is_some_and(|since| !self.is_modified_since(since)),
+        };
+        if not_modified {
+            return response.status(Status::NotModified).ok();
+        }
+
+        // a range of an older version of the file is not combined with the current one
+        let range = headers
+            .get_one("Range")
+            .filter(|_| {
+                headers
+                    .get_one("If-Range")
+                    .is_none_or(|if_range| if_range == self.etag || if_range == last_modified)
+            })
+            .and_then(|range| parse_range(range, self.len));
+
+        let mut file = fs::File::open(&self.path).map_err(|_| Status::NotFound)?;
+        match range {
+            Some(Ok((start, end))) => {
+                let end = end.min(start + MAX_RANGE - 1);
+                let mut body = vec![0; (end - start + 1) as usize];
+                file.seek(SeekFrom::Start(start))
+                    .and_then(|_| file.read_exact(&mut body))
+                    .map_err(|_| Status::InternalServerError)?;
+                response
+                    .status(Status::PartialContent)
+                    .raw_header(
+                        "Content-Range",
+                        format!("bytes {}-{}/{}", start, end, self.len),
+                    )
+                    .sized_body(body.len(), Cursor::new(body));
+            }
+            Some(Err(())) => {
+                response
+                    .status(Status::RangeNotSatisfiable)
+                    .raw_header("Content-Range", format!("bytes */{}", self.len));
+            }
+            None => {
+                response.sized_body(self.len as usize, rocket::tokio::fs::File::from_std(file));
+            }
+        }
+        response.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_ranges() {
+        assert_eq!(parse_range("bytes=0-499", 1000), Some(Ok((0, 499))));
+        assert_eq!(parse_range("bytes=500-", 1000), Some(Ok((500, 999))));
+        assert_eq!(parse_range("bytes=-200", 1000), Some(Ok((800, 999))));
+        assert_eq!(parse_range("bytes=900-2000", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=5-1", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+    }
+
+    #[test]
+    fn it_formats_http_dates() {
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1729166400);
+        assert_eq!(http_date(time), "Thu, 17 Oct 2024 12:00:00 GMT");
+    }
+
+    #[get("/<private>")]
+    fn whale(private: bool, root: &rocket::State<PathBuf>) -> Option<MediaFile> {
+        MediaFile::open_in(root, &root.join("whale.jpg"), ContentType::JPEG, "a1b2c3d4")
+            .map(|file| file.private(private))
+    }
+
+    #[test]
+    fn it_keeps_private_files_out_of_shared_caches() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("whale.jpg"), "jpg").unwrap();
+        let rocket = rocket::build()
+            .manage(temp_dir.path().to_path_buf())
+            .mount("/", routes![whale]);
+        let client = rocket::local::blocking::Client::tracked(rocket).unwrap();
+
+        let cache_control = |path: &str| {
+            let response = client.get(path).dispatch();
+            response
+                .headers()
+                .get_one("Cache-Control")
+                .map(String::from)
+        };
+        assert_eq!(
+            cache_control("/false").as_deref(),
+            Some("public, max-age=31536000, immutable")
+        );
+        assert_eq!(cache_control("/true").as_deref(), Some("private, no-store"));
+    }
+}
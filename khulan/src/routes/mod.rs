@@ -1,65 +1,211 @@
+use crate::cms::language::LanguagePrefix;
 use crate::cms::model::Model;
-use crate::cms::site::Site;
-use rocket::http::Status;
+use crate::cms::site::{Resolved, Site};
+use crate::cms::translation::TranslationReport;
+use crate::routes::auth::{Admin, Role, User};
+use crate::routes::guards::{PreferredLanguage, LANGUAGE_COOKIE};
+use crate::routes::media::MediaFile;
+use rocket::http::uri::Host;
+use rocket::http::{Cookie, CookieJar, SameSite, Status};
+use rocket::response::content::RawXml;
+use rocket::response::Redirect;
 use rocket::serde::json::Json;
-use rocket::{Config, State};
+use rocket::{Config, Either, State};
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
-#[get("/<path..>")]
-pub fn index(path: PathBuf, site_state: &State<Arc<RwLock<Site>>>) -> Result<String, Status> {
-    // iterate between read and write locks to make requests that do not detect
-    // changes to the site run faster and not block the site with the write lock
-    let changes;
-    {
-        //  a read lock to check if the site needs refresh
-        let site = site_state.read().unwrap();
-        changes = site.changes();
-        // println!("CHANGES: {:?}", changes.clone());
-    }
+pub mod auth;
+pub mod guards;
+pub mod media;
 
-    if changes.len() > 0 {
-        let mut site = site_state.write().unwrap();
-        site.load(&changes);
+/// Load the changes of the content before a request is served. Iterates between read
+/// and write locks, so requests without changes run faster and do not block the site.
+pub fn refresh(site_state: &RwLock<Site>) {
+    let changes = site_state.read().unwrap().changes();
+    if !changes.is_empty() {
+        site_state.write().unwrap().load(&changes);
     }
+}
+
+// an explicit rank, so routes for templates like `Page<T>` can be ranked before it
+#[get("/<path..>", rank = 2)]
+pub fn index(
+    path: PathBuf,
+    host: Option<&Host<'_>>,
+    language: PreferredLanguage,
+    user: Option<User>,
+    cookies: &CookieJar<'_>,
+    site_state: &State<Arc<RwLock<Site>>>,
+) -> Result<Either<(Status, String), Redirect>, Status> {
+    refresh(site_state);
 
     let site = site_state.read().unwrap();
-    let page = site.page(&path.to_string_lossy().to_string(), None);
+    let path = path.to_string_lossy();
+    let host = host.map(|host| host.domain().as_str());
+
+    let preferred = match (site.language_prefix(), language.chosen) {
+        // a path without prefix is the default language the visitor has chosen before
+        (LanguagePrefix::NotDefault, true) => None,
+        _ => language.code.as_deref(),
+    };
+    if let Some(page) = site.negotiate_at(host, &path, preferred) {
+        // not permanent as it depends on the visitor
+        return Ok(Either::Right(Redirect::found(page.url())));
+    }
+
+    let page = site
+        .resolve_at(host, &path)
+        .filter(|page| site.is_visible(page.model()));
+    if page
+        .as_ref()
+        .is_some_and(|page| site.is_members_only(page.model()))
+        && user.is_none()
+    {
+        return Err(Status::Unauthorized);
+    }
 
     // TODO: change to template as currently this is preventing the static files to be served
     match page {
-        Some(page) => Ok(page.title().to_string()),
-        None => Err(Status::NotFound),
+        Some(Resolved::Page(page)) => {
+            if !page.language().is_empty() {
+                cookies.add(
+                    Cookie::build((LANGUAGE_COOKIE, page.language().to_string()))
+                        .path("/")
+                        .same_site(SameSite::Lax),
+                );
+            }
+            Ok(Either::Left((Status::Ok, page.title().to_string())))
+        }
+        Some(Resolved::Redirect(page)) => Ok(Either::Right(Redirect::permanent(page.url()))),
+        // the error page in the language of the prefix, the visitor or the default
+        None => {
+            let first = path.split('/').next().unwrap_or("");
+            let lang = host
+                .and_then(|host| site.language_of_host(host))
+                .map(|language| language.code())
+                .or(site.languages.get(first).map(|language| language.code()))
+                .or(language.code.as_deref());
+            match site.error_page(lang) {
+                Some(page) => Ok(Either::Left((Status::NotFound, page.title().to_string()))),
+                None => Err(Status::NotFound),
+            }
+        }
     }
 }
 
 #[get("/api/pages/<search..>")]
 pub fn api_page(
     search: PathBuf,
+    host: Option<&Host<'_>>,
+    user: Option<User>,
     site_state: &State<Arc<RwLock<Site>>>,
-) -> Result<Json<Model>, Status> {
-    // iterate between read and write locks to make requests that do not detect
-    // changes to the site run faster and not block the site with the write lock
-    let changes;
+) -> Result<Either<Json<Model>, Redirect>, Status> {
+    refresh(site_state);
+
+    let site = site_state.read().unwrap();
+    let host = host.map(|host| host.domain().as_str());
+    let page = site
+        .resolve_at(host, &search.to_string_lossy())
+        .filter(|page| site.is_visible(page.model()));
+    if page
+        .as_ref()
+        .is_some_and(|page| site.is_members_only(page.model()))
+        && user.is_none()
     {
-        //  a read lock to check if the site needs refresh
-        let site = site_state.read().unwrap();
-        changes = site.changes();
-        // println!("CHANGES: {:?}", changes.clone());
+        return Err(Status::Unauthorized);
     }
 
-    if changes.len() > 0 {
-        let mut site = site_state.write().unwrap();
-        site.load(&changes);
+    match page {
+        Some(Resolved::Page(page)) => Ok(Either::Left(Json(page.clone()))),
+        // on the domain of the language of the page
+        Some(Resolved::Redirect(page)) => Ok(Either::Right(Redirect::permanent(format!(
+            "{}/api/pages/{}",
+            page.origin(),
+            page.url_path()
+        )))),
+        None => Err(Status::NotFound),
     }
+}
+
+/// the file with its metadata like `/api/files/blog/whales/whale.jpg`, with the
+/// dimensions, exif data and placeholders of images
+#[get("/api/files/<search..>?<lang>")]
+pub fn api_file(
+    search: PathBuf,
+    lang: Option<&str>,
+    user: Option<User>,
+    site_state: &State<Arc<RwLock<Site>>>,
+) -> Result<Json<Model>, Status> {
+    refresh(site_state);
 
     let site = site_state.read().unwrap();
-    let page = site.page(&search.to_string_lossy().to_string(), None);
+    let file = site
+        .file(&search.to_string_lossy(), lang)
+        .filter(|file| site.is_visible(file))
+        .ok_or(Status::NotFound)?;
+    match site.is_members_only(file) && user.is_none() {
+        true => Err(Status::Unauthorized),
+        false => Ok(Json(file.clone())),
+    }
+}
 
-    match page {
-        Some(page) => Ok(Json(page.clone())),
-        None => Err(Status::NotFound),
+/// the files of the pages and the site, their thumbs and the avatars of users under
+/// urls that change with the file, redirecting to the current url for an outdated hash
+#[get("/media/<path..>")]
+pub fn media_file(
+    path: PathBuf,
+    user: Option<User>,
+    site_state: &State<Arc<RwLock<Site>>>,
+) -> Result<Either<MediaFile, Redirect>, Status> {
+    refresh(site_state);
+
+    let site = site_state.read().unwrap();
+    let path = path.to_string_lossy();
+    if let Some(user) = site.resolve_avatar(&path) {
+        return match user {
+            Resolved::Page(user) => MediaFile::avatar(user)
+                .map(Either::Left)
+                .ok_or(Status::NotFound),
+            Resolved::Redirect(user) => user
+                .avatar_url(&site)
+                .map(|url| Either::Right(Redirect::found(url)))
+                .ok_or(Status::NotFound),
+        };
+    }
+    // the files and thumbs of members only pages
+    let members_only = site
+        .media_page_id(&path)
+        .is_some_and(|id| site.is_members_only_id(&id));
+    if members_only && user.is_none() {
+        return Err(Status::Unauthorized);
     }
+    match site.resolve_media(&path) {
+        Some(Resolved::Page(file)) => MediaFile::open(&site, file)
+            .map(|file| Either::Left(file.private(members_only)))
+            .ok_or(Status::NotFound),
+        Some(Resolved::Redirect(file)) => file
+            .media_url(&site)
+            .map(|url| Either::Right(Redirect::found(url)))
+            .ok_or(Status::NotFound),
+        // thumbs of the images
+        None => site
+            .thumb_file(&path)
+            .and_then(|thumb| MediaFile::generated(&site, &thumb))
+            .map(|thumb| Either::Left(thumb.private(members_only)))
+            .ok_or(Status::NotFound),
+    }
+}
+
+// only for admins, since the report lists the content files of all pages
+#[get("/api/translations")]
+pub fn api_translations(
+    site_state: &State<Arc<RwLock<Site>>>,
+    _admin: Role<Admin>,
+) -> Json<TranslationReport> {
+    refresh(site_state);
+
+    let site = site_state.read().unwrap();
+    Json(site.translation_report())
 }
 
 #[cfg(feature = "robots_txt")]
@@ -75,7 +221,8 @@ pub fn robots_txt(site: &State<Arc<RwLock<Site>>>, config: &State<Config>) -> St
 
 #[cfg(feature = "sitemap_xml")]
 #[get("/sitemap.xml")]
-pub fn sitemap_xml(site: &State<Arc<RwLock<Site>>>, config: &State<Config>) -> String {
+pub fn sitemap_xml(site: &State<Arc<RwLock<Site>>>, host: Option<&Host<'_>>) -> RawXml<String> {
     let site = site.read().unwrap();
-    String::from("TODO") // TODO: sitemap
+    let host = host.map(|host| host.domain().as_str());
+    RawXml(site.sitemap(host).to_xml())
 }
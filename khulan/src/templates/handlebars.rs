@@ -0,0 +1,430 @@
+use crate::cms::deserializer::parse_datetime;
+use crate::cms::format::{DateStyle, Locale};
+use crate::cms::site::Site;
+use crate::cms::thumb::{ThumbFormat, ThumbOptions};
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderErrorReason,
+};
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+
+/// Register the helpers of khulan, like `{{t "read.more"}}`, for templates rendered
+/// with `rocket_dyn_templates`:
+/// `Template::custom(|engines| register_helpers(&mut engines.handlebars, site.clone()))`
+pub fn register_helpers(handlebars: &mut Handlebars, site: Arc<RwLock<Site>>) {
+    handlebars.register_helper(
+        "t",
+        Box::new(Translate {
+            site: site.clone(),
+            count: false,
+        }),
+    );
+    handlebars.register_helper(
+        "tc",
+        Box::new(Translate {
+            site: site.clone(),
+            count: true,
+        }),
+    );
+    for (name, kind) in [
+        ("date", FormatKind::Date),
+        ("time", FormatKind::Time),
+        ("ago", FormatKind::Ago),
+        ("number", FormatKind::Number),
+        ("currency", FormatKind::Currency),
+    ] {
+        handlebars.register_helper(
+            name,
+            Box::new(Format {
+                site: site.clone(),
+                kind,
+            }),
+        );
+    }
+    handlebars.register_helper(
+        "thumb",
+        Box::new(Thumb {
+            site: site.clone(),
+            srcset: false,
+        }),
+    );
+    handlebars.register_helper(
+        "srcset",
+        Box::new(Thumb {
+            site: site.clone(),
+            srcset: true,
+        }),
+    );
+}
+
+/// `{{t "hello" name="Khulan"}}` and `{{tc "comments" count}}` translate language
+/// variables into the `lang` of the hash, the `language` of the context or else the
+/// default language. The other values of the hash fill the placeholders.
+struct Translate {
+    site: Arc<RwLock<Site>>,
+    count: bool,
+}
+
+impl HelperDef for Translate {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let name = if self.count { "tc" } else { "t" };
+        let key = h
+            .param(0)
+            .and_then(|param| param.value().as_str())
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex(name, 0))?;
+
+        let site = self.site.read().unwrap();
+        let lang = language(h, ctx)
+            .or_else(|| site.default_language().map(|l| l.code().to_string()))
+            .unwrap_or_default();
+        let data: Vec<(String, String)> = h
+            .hash()
+            .iter()
+            .filter(|(name, _)| **name != "lang")
+            .map(|(name, value)| (name.to_string(), text(value.value())))
+            .collect();
+        let data: Vec<(&str, &str)> = data
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+
+        let translated = match self.count {
+            true => {
+                let count = h
+                    .param(1)
+                    .and_then(|param| param.value().as_u64())
+                    .ok_or(RenderErrorReason::ParamNotFoundForIndex(name, 1))?;
+                site.languages.translate_count(&lang, key, count, &data)
+            }
+            false => site.languages.translate(&lang, key, &data),
+        };
+        // the key shows which variable is missing
+        out.write(translated.as_deref().unwrap_or(key))?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum FormatKind {
+    Date,
+    Time,
+    Ago,
+    Number,
+    Currency,
+}
+
+/// `{{date value "long"}}`, `{{date value format="%-d. %B"}}`, `{{time value}}`,
+/// `{{ago value}}`, `{{number value 2}}` and `{{currency value "EUR"}}` format
+/// kirby dates and numbers in the locale of the language, like `t` finds it.
+struct Format {
+    site: Arc<RwLock<Site>>,
+    kind: FormatKind,
+}
+
+impl HelperDef for Format {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let name = h.name();
+        let value = h
+            .param(0)
+            .map(|param| text(param.value()))
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex("format", 0))?;
+        let option = h.param(1).map(|param| text(param.value()));
+
+        let locale = {
+            let site = self.site.read().unwrap();
+            let language = language(h, ctx)
+                .and_then(|lang| site.language(&lang))
+                .or_else(|| site.default_language());
+            Locale::get(language.map_or("en", |language| language.locale()))
+        };
+
+        let formatted = match self.kind {
+            FormatKind::Date | FormatKind::Time | FormatKind::Ago => {
+                // empty dates are rendered empty like in kirby
+                let datetime = match parse_datetime(&value) {
+                    Some(datetime) => datetime,
+                    None if value.trim().is_empty() => return Ok(()),
+                    None => return Err(RenderErrorReason::InvalidParamType("a kirby date").into()),
+                };
+                match self.kind {
+                    FormatKind::Time => locale.time(&datetime),
+                    FormatKind::Ago => locale.relative(&datetime, &chrono::Utc::now().naive_utc()),
+                    _ => {
+                        match h.hash_get("format") {
+                            Some(format) => locale
+                                .format(&datetime, &text(format.value()))
+                                .map_err(|_| {
+                                    RenderErrorReason::InvalidParamType("a strftime format")
+                                })?,
+                            None => locale.date(
+                                &datetime,
+                                DateStyle::from(option.as_deref().unwrap_or("medium")),
+                            ),
+                        }
+                    }
+                }
+            }
+            FormatKind::Number | FormatKind::Currency => {
+                let number = value.trim().parse::<f64>().map_err(|_| {
+                    RenderErrorReason::ParamTypeMismatchForName(
+                        "format",
+                        name.to_string(),
+                        "number".to_string(),
+                    )
+                })?;
+                match self.kind {
+                    FormatKind::Currency => {
+                        locale.currency(number, option.as_deref().unwrap_or("EUR"))
+                    }
+                    _ => locale.number(
+                        number,
+                        option
+                            .and_then(|decimals| decimals.parse().ok())
+                            .unwrap_or(0),
+                    ),
+                }
+            }
+        };
+        out.write(&formatted)?;
+        Ok(())
+    }
+}
+
+/// `{{thumb "blog/whales/whale.jpg" width=300 height=200 crop=true}}` renders the url of
+/// a thumb and `{{srcset "blog/whales/whale.jpg" "300, 600, 900"}}` the `srcset` of
+/// thumbs of the widths. Both take the `quality` and the `format`, like `"webp"`.
+struct Thumb {
+    site: Arc<RwLock<Site>>,
+    srcset: bool,
+}
+
+impl HelperDef for Thumb {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let name = h.name();
+        let id = h
+            .param(0)
+            .map(|param| text(param.value()))
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex("thumb", 0))?;
+        let number = |name: &str| h.hash_get(name).and_then(|value| value.value().as_u64());
+        let options = ThumbOptions {
+            width: number("width").map(|width| width as u32),
+            height: number("height").map(|height| height as u32),
+            crop: h
+                .hash_get("crop")
+                .is_some_and(|crop| crop.value().as_bool() == Some(true)),
+            quality: number("quality").map(|quality| quality.min(100) as u8),
+            format: match h.hash_get("format") {
+                Some(format) => Some(ThumbFormat::from_extension(&text(format.value())).ok_or(
+                    RenderErrorReason::InvalidParamType("a format like \"webp\""),
+                )?),
+                None => None,
+            },
+        };
+
+        let site = self.site.read().unwrap();
+        let lang = language(h, ctx);
+        // missing files render empty like in kirby
+        let Some(file) = site.file(&id, lang.as_deref()) else {
+            return Ok(());
+        };
+        let rendered = match self.srcset {
+            true => {
+                let widths = h
+                    .param(1)
+                    .map(|param| text(param.value()))
+                    .ok_or(RenderErrorReason::ParamNotFoundForIndex("srcset", 1))?;
+                let widths: Vec<u32> = widths
+                    .split(',')
+                    .map(|width| width.trim().parse::<u32>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| {
+                        RenderErrorReason::ParamTypeMismatchForName(
+                            "srcset",
+                            name.to_string(),
+                            "widths like \"300, 600\"".to_string(),
+                        )
+                    })?;
+                file.srcset(&site, &widths, &options)
+            }
+            false => file
+                .thumb(&site, &options)
+                .map(|thumb| thumb.url)
+                .unwrap_or_default(),
+        };
+        out.write(&rendered)?;
+        Ok(())
+    }
+}
+
+/// the language from the hash or the context, like the `language` of a model
+pub(crate) fn language(h: &Helper, ctx: &Context) -> Option<String> {
+    h.hash_get("lang")
+        .map(|lang| text(lang.value()))
+        .or_else(|| ctx.data().get("language").map(text))
+        .or_else(|| ctx.data().get("lang").map(text))
+        .filter(|lang| !lang.is_empty())
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_string(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::language::{Language, Languages, Variable};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn it_translates_in_templates() {
+        let mut site = Site::new(None, None, None);
+        let mut en = Language::new("en");
+        en.default = true;
+        en.translations = BTreeMap::from([
+            (
+                "hello".to_string(),
+                Variable::Text("Hello {{ name }}".to_string()),
+            ),
+            (
+                "posts".to_string(),
+                Variable::Counted(vec![
+                    "No posts".to_string(),
+                    "{{ count }} posts".to_string(),
+                ]),
+            ),
+        ]);
+        let mut de = Language::new("de");
+        de.translations = BTreeMap::from([(
+            "hello".to_string(),
+            Variable::Text("Hallo {{ name }}".to_string()),
+        )]);
+        site.languages = Languages::new(vec![en, de]);
+
+        let mut handlebars = Handlebars::new();
+        register_helpers(&mut handlebars, Arc::new(RwLock::new(site)));
+        let render =
+            |template: &str, data: Value| handlebars.render_template(template, &data).unwrap();
+
+        assert_eq!(
+            render(
+                r#"{{t "hello" name=name}}"#,
+                json!({"language": "de", "name": "Khulan"})
+            ),
+            "Hallo Khulan"
+        );
+        assert_eq!(
+            render(r#"{{t "hello" lang="en" name="you"}}"#, json!({})),
+            "Hello you"
+        );
+        assert_eq!(
+            render(r#"{{tc "posts" 3 lang="de"}}"#, json!({})),
+            "3 posts"
+        );
+        assert_eq!(render(r#"{{t "missing"}}"#, json!({})), "missing");
+    }
+
+    #[test]
+    fn it_formats_in_templates() {
+        let mut site = Site::new(None, None, None);
+        let mut en = Language::new("en");
+        en.default = true;
+        let mut de = Language::new("de");
+        de.locale = "de_DE".to_string();
+        site.languages = Languages::new(vec![en, de]);
+
+        let mut handlebars = Handlebars::new();
+        register_helpers(&mut handlebars, Arc::new(RwLock::new(site)));
+        let render =
+            |template: &str, data: Value| handlebars.render_template(template, &data).unwrap();
+
+        let data = json!({"language": "de", "date": "2024-10-17 09:30", "price": "1234.5"});
+        assert_eq!(
+            render(r#"{{date date "long"}}"#, data.clone()),
+            "17. Oktober 2024"
+        );
+        assert_eq!(
+            render(r#"{{date date format="%a, %-d. %b"}}"#, data.clone()),
+            "Do., 17. Okt."
+        );
+        assert_eq!(
+            render(r#"{{time date lang="en"}}"#, data.clone()),
+            "9:30 AM"
+        );
+        assert_eq!(render(r#"{{number price 2}}"#, data.clone()), "1.234,50");
+        assert_eq!(
+            render(r#"{{currency price "USD" lang="en"}}"#, data.clone()),
+            "$1,234.50"
+        );
+        assert!(render(r#"{{ago date}}"#, data.clone()).starts_with("vor "));
+        assert_eq!(render(r#"{{date missing}}"#, data.clone()), "");
+        assert!(handlebars
+            .render_template(r#"{{date date format="%-d %Q"}}"#, &data)
+            .is_err());
+    }
+
+    #[test]
+    fn it_renders_thumbs_in_templates() {
+        use crate::cms::model::{ModelBuilder, ModelKind};
+        use crate::cms::site::SiteBuilder;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path().join("storage/content/blog/1_whales");
+        std::fs::create_dir_all(&folder).unwrap();
+        image::RgbImage::new(40, 20)
+            .save(folder.join("whale.png"))
+            .unwrap();
+
+        let mut site = SiteBuilder::new()
+            .dir(&temp_dir.path().to_path_buf())
+            .build();
+        site.languages = Languages::from_codes(&["en"]);
+        site.insert(
+            ModelBuilder::new()
+                .kind(&ModelKind::File)
+                .path("blog/whales")
+                .language("en")
+                .root(&folder.join("whale.png").to_string_lossy())
+                .build(),
+        );
+
+        let mut handlebars = Handlebars::new();
+        register_helpers(&mut handlebars, Arc::new(RwLock::new(site)));
+        let render = |template: &str| handlebars.render_template(template, &json!({})).unwrap();
+
+        let url = render(r#"{{thumb "blog/whales/whale.png" width=20 format="webp"}}"#);
+        assert!(url.starts_with("http://localhost:8000/media/pages/blog/whales/"));
+        assert!(url.ends_with("/whale-20x.webp"));
+        let srcset = render(r#"{{srcset "blog/whales/whale.png" "10, 20"}}"#);
+        assert!(srcset.ends_with("/whale-20x.png 20w"));
+        assert_eq!(
+            render(r#"{{thumb "blog/whales/missing.png" width=20}}"#),
+            ""
+        );
+    }
+}
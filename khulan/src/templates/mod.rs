@@ -0,0 +1,2 @@
+#[cfg(feature = "handlebars")]
+pub mod handlebars;
@@ -0,0 +1,69 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Expr, ExprLit, Fields, Lit, Meta};
+
+/// Derive `khulan::cms::page::KhulanPage` for a struct that also derives `Deserialize`.
+/// The template is set with `#[template = "post"]` and defaults to the lowercase struct name.
+/// Each named field gets a getter of the same name.
+#[proc_macro_derive(KhulanPage, attributes(template))]
+pub fn derive_khulan_page(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut template = name.to_string().to_lowercase();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("template")) {
+        match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(value),
+                    ..
+                }) => template = value.value(),
+                _ => {
+                    return syn::Error::new_spanned(&meta.value, "expected #[template = \"...\"]")
+                        .to_compile_error()
+                        .into()
+                }
+            },
+            _ => {
+                return syn::Error::new_spanned(attr, "expected #[template = \"...\"]")
+                    .to_compile_error()
+                    .into()
+            }
+        }
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+            _ => vec![],
+        },
+        _ => {
+            return syn::Error::new_spanned(name, "KhulanPage can only be derived for structs")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    let getters = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let getter = format_ident!("{}", ident.to_string().trim_start_matches("r#"));
+        let ty = &field.ty;
+        quote! {
+            pub fn #getter(&self) -> &#ty {
+                &self.#ident
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::khulan::cms::page::KhulanPage for #name #ty_generics #where_clause {
+            const TEMPLATE: &'static str = #template;
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#getters)*
+        }
+    }
+    .into()
+}
@@ -0,0 +1,16 @@
+<?php
+
+return [
+    'code' => 'de',
+    'default' => false,
+    'direction' => 'ltr',
+    'locale' => [
+        LC_ALL => 'de_DE'
+    ],
+    'name' => 'Deutsch',
+    'url' => NULL,
+    'translations' => [
+        'read.more' => 'Weiterlesen',
+        'posts' => ['Keine Beiträge', 'Ein Beitrag', '{{ count }} Beiträge']
+    ]
+];
@@ -0,0 +1,16 @@
+<?php
+
+return [
+    'code' => 'en',
+    'default' => true,
+    'direction' => 'ltr',
+    'locale' => [
+        LC_ALL => 'en_US'
+    ],
+    'name' => 'English',
+    'url' => NULL,
+    'translations' => [
+        'read.more' => 'Read more',
+        'posts' => ['No posts', 'One post', '{{ count }} posts']
+    ]
+];
@@ -1,11 +1,14 @@
 #[macro_use]
 extern crate rocket;
 
+use khulan::routes::auth::{api_auth, login, logout, Throttle};
 use khulan::routes::*;
-use khulan::site;
+use khulan::templates::handlebars::register_helpers;
+use khulan::{site, KhulanPage, Page};
 use maud::{html, Markup};
 use rocket::fairing::AdHoc;
 use rocket::fs::FileServer;
+use rocket::serde::Deserialize;
 use rocket::Config;
 use rocket_dyn_templates::{context, Template};
 use std::sync::{Arc, RwLock};
@@ -31,6 +34,19 @@ fn tmaud() -> Markup {
     }
 }
 
+#[derive(Deserialize, KhulanPage)]
+#[serde(crate = "rocket::serde")]
+#[template = "post"]
+struct BlogPost {
+    title: String,
+}
+
+// ranked before the generic `index` route which handles all other templates
+#[get("/<_..>", rank = 1)]
+fn post(page: Page<BlogPost>) -> String {
+    page.title().to_string()
+}
+
 #[launch]
 fn rocket() -> _ {
     dotenvy::dotenv().ok();
@@ -44,11 +60,31 @@ fn rocket() -> _ {
     // println!("Models loaded: {:?}", site.models.len());
     // println!("Last modified: {:?}", site.last_modified);
 
+    let site = Arc::new(RwLock::new(site));
+    let helpers_site = site.clone();
+
     rocket::build()
-        .manage(Arc::new(RwLock::new(site)))
-        .mount("/", routes![thbs, tmaud])
-        .mount("/", routes![index, api_page, robots_txt, sitemap_xml])
+        .manage(site)
+        .manage(Throttle::default())
+        .mount("/", routes![thbs, tmaud, post])
+        .mount(
+            "/",
+            routes![
+                index,
+                api_page,
+                api_file,
+                api_translations,
+                media_file,
+                login,
+                logout,
+                api_auth,
+                robots_txt,
+                sitemap_xml
+            ],
+        )
         .mount("/", FileServer::from("./public"))
-        .attach(Template::fairing())
+        .attach(Template::custom(move |engines| {
+            register_helpers(&mut engines.handlebars, helpers_site.clone())
+        }))
         .attach(AdHoc::config::<Config>())
 }